@@ -1,12 +1,16 @@
 use serde::Deserialize;
-use serde_yaml;
 use std::fs::{self, File};
 use std::io::Write;
 
 #[derive(Deserialize, Debug)]
 #[allow(non_snake_case)]
 struct Config {
-    L: usize,
+    L: Option<usize>,
+    Lx: Option<usize>,
+    Ly: Option<usize>,
+    periodic: Option<bool>,
+    lattice: Option<String>,
+    diagonals: Option<bool>,
     precision: String,
     n_threads: usize,
     n_tries: usize,
@@ -17,6 +21,7 @@ struct Config {
 fn build_config_file(config: &Config) -> std::io::Result<()> {
     let mut file = File::create("./src/config.rs")?;
     writeln!(file, "// This file is autogenerated from build.rs")?;
+    writeln!(file, "use crate::lattice::Lattice;")?;
 
     match config.precision.as_str() {
         "f32" => writeln!(file, "pub type Precision = f32;")?,
@@ -28,11 +33,40 @@ fn build_config_file(config: &Config) -> std::io::Result<()> {
         _ => panic!("Precision {} not supported.", config.precision),
     }
 
-    writeln!(file, "pub const L: usize = {};", config.L)?;
+    let lx = config
+        .Lx
+        .or(config.L)
+        .expect("Missing Lx (or L) in config.yaml");
+    let ly = config
+        .Ly
+        .or(config.L)
+        .expect("Missing Ly (or L) in config.yaml");
+    writeln!(file, "pub const LX: usize = {};", lx)?;
+    writeln!(file, "pub const LY: usize = {};", ly)?;
+    writeln!(
+        file,
+        "pub const PERIODIC: bool = {};",
+        config.periodic.unwrap_or(false)
+    )?;
+    let lattice = match config.lattice.as_deref().unwrap_or("square") {
+        "square" => "Square",
+        "triangular" => "Triangular",
+        "honeycomb" => "Honeycomb",
+        other => panic!("Lattice {} not supported.", other),
+    };
+    writeln!(file, "pub const LATTICE: Lattice = Lattice::{};", lattice)?;
+    writeln!(
+        file,
+        "pub const DIAGONALS: bool = {};",
+        config.diagonals.unwrap_or(false)
+    )?;
     writeln!(file, "pub const N_THREADS: usize = {};", config.n_threads)?;
     writeln!(file, "pub const N_TRIES: usize = {};", config.n_tries)?;
-    writeln!(file, "pub const N_RES: usize = (L-1)*(L-2)+L*(L-2) + 2*L;")?;
-    writeln!(file, "pub const N_UNK: usize = L * (L - 2);")?;
+    writeln!(
+        file,
+        "pub const N_RES: usize = LATTICE.n_bonds(LX, LY - 2);"
+    )?;
+    writeln!(file, "pub const N_UNK: usize = LX * (LY - 2);")?;
 
     Ok(())
 }
@@ -4,7 +4,35 @@ using Plots
 using Plots.PlotMeasures
 using LaTeXStrings
 
-function compute_metrics(surfaces_file)
+# Height of the contour on a periodic sample. The rows are unwrapped along the order of the
+# contour, a step longer than half the period being one across the seam, and h is the spread of
+# the columns over the first full period of the unwrapped contour.
+function periodic_height(rows, cols, period)
+    if isempty(rows)
+        return 0
+    end
+    unwrapped = zeros(Int64, length(rows))
+    unwrapped[1] = rows[1]
+    for i in 2:length(rows)
+        step = rows[i] - rows[i-1]
+        if step > period / 2
+            step -= period
+        elseif step < -period / 2
+            step += period
+        end
+        unwrapped[i] = unwrapped[i-1] + step
+    end
+    full = findfirst(u -> abs(u - unwrapped[1]) >= period, unwrapped)
+    window = cols[1:(full === nothing ? length(cols) : full - 1)]
+    return maximum(window) - minimum(window)
+end
+
+# A contour that crosses the seam downwards, past the end of its first period, and one that
+# crosses it upwards
+@assert periodic_height([4, 5, 0, 1, 2, 3, 4, 5], [2, 2, 3, 3, 4, 4, 9, 9], 6) == 2
+@assert periodic_height([1, 0, 5, 4], [0, 1, 1, 2], 6) == 2
+
+function compute_metrics(surfaces_file; period=nothing)
     ℓ::Vector{Int64} = []
     h::Vector{Int64} = []
     new_ℓ = 0
@@ -14,9 +42,17 @@ function compute_metrics(surfaces_file)
             for line in eachline(file)
                 try
                     coords = split(line, " ")
-                    cols = coords[2:2:end]
+                    rows = parse.(Int32, coords[1:2:end])
+                    cols = parse.(Int32, coords[2:2:end])
+                    if isempty(cols)
+                        continue
+                    end
                     new_ℓ = length(cols)
-                    new_h = abs(parse(Int32, cols[1]) - parse(Int32, cols[end]))
+                    if period === nothing
+                        new_h = abs(cols[1] - cols[end])
+                    else
+                        new_h = periodic_height(rows, cols, period)
+                    end
                     push!(ℓ, new_ℓ)
                     push!(h, new_h)
                 catch e
@@ -42,11 +78,26 @@ function load_files(srcdir)
             continue
         end
         L = parse(Int64, m.captures[1])
-        dist = String(m.captures[2])
-        param = parse(Float64, m.captures[4])
+        # The scaling analysis only uses square samples
+        if m.captures[2] !== nothing && parse(Int64, m.captures[2]) != L
+            continue
+        end
+        # Only samples of the selected lattice ("" square, "t" triangular, "h" honeycomb, followed
+        # by "d" with all the diagonals) and transverse boundary, whose rows wrap with period L
+        if m.captures[3] != lattice || (m.captures[4] == "p") != periodic
+            continue
+        end
+        # Only samples with the selected dilution and correlations of the disorder, such as
+        # "_corr(xi=4.000)", none by default
+        if m.captures[8] != modifiers
+            continue
+        end
+        period = periodic ? L : nothing
+        dist = String(m.captures[5])
+        param = parse(Float64, m.captures[7])
 
         surfaces_file = joinpath(srcdir, file)
-        ℓ, h = compute_metrics(surfaces_file)
+        ℓ, h = compute_metrics(surfaces_file; period=period)
 
         ℓs[dist, L, param] = ℓ
         hs[dist, L, param] = h
@@ -67,7 +118,10 @@ Lx = Dict(
     "Weibull" => Lx_wei
 )
 
-pattern = r"^isosurfaces_L(\d+)_(\w+)\((\w)=([\d.]+)\).out$"
+pattern = r"^isosurfaces_L(\d+)(?:x(\d+))?([th]?d?)(p?)_(\w+)\((\w)=([\d.]+)\)(.*).out$"
+lattice = ""
+periodic = false
+modifiers = ""
 # srcdir = "C:/Users/javgua/Desktop/TFM/outputs/isosurfaces/"
 srcdir = "C:/Users/javgua/Desktop/TFM/solver/runner/isosurfaces/"
 
@@ -2,8 +2,8 @@ using Plots
 using Plots.PlotMeasures
 using LaTeXStrings
 
-function load_surface(file, L, n_surface)
-    surface = zeros(Float64, L, L)
+function load_surface(file, Lx, Ly, n_surface)
+    surface = zeros(Float64, Lx, Ly)
 
     open(file) do f
         n_line = 0
@@ -26,18 +26,21 @@ function load_surface(file, L, n_surface)
     return surface
 end
 
-function find_file(srcdir, L, dist, param)
+function find_file(srcdir, Lx, Ly, dist, param; lattice="", periodic=false)
     files = readdir(srcdir)
     for file in files
         m = match(pattern, file)
         if m === nothing
             continue
         end
-        L_ = parse(Int64, m.captures[1])
-        dist_ = String(m.captures[2])
-        param_ = parse(Float64, m.captures[4])
+        Lx_ = parse(Int64, m.captures[1])
+        Ly_ = m.captures[2] === nothing ? Lx_ : parse(Int64, m.captures[2])
+        lattice_ = m.captures[3]
+        periodic_ = m.captures[4] == "p"
+        dist_ = String(m.captures[5])
+        param_ = parse(Float64, m.captures[7])
 
-        if L == L_ && dist == dist_ && param == param_
+        if Lx == Lx_ && Ly == Ly_ && lattice == lattice_ && periodic == periodic_ && dist == dist_ && param == param_
             return joinpath(srcdir, file)
         end
     end
@@ -45,6 +48,7 @@ function find_file(srcdir, L, dist, param)
 end
 
 L = 50
+Ly = L
 dist = "Inverse"
 param = 7
 
@@ -52,9 +56,9 @@ param_names = Dict("Weibull" => "k", "Inverse" => "a")
 param_name = param_names[dist]
 
 outdir = "C:/Users/javgua/Desktop/TFM/outputs/isosurfaces/"
-pattern = r"^isosurfaces_L(\d+)_(\w+)\((\w)=([\d.]+)\).out$"
-file = find_file(outdir, L, dist, param)
-surface = load_surface(file, L, 104)
+pattern = r"^isosurfaces_L(\d+)(?:x(\d+))?([th]?d?)(p?)_(\w+)\((\w)=([\d.]+)\).out$"
+file = find_file(outdir, L, Ly, dist, param)
+surface = load_surface(file, L, Ly, 104)
 
 cmap = cgrad([:white, :black])
 
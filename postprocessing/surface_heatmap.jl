@@ -2,8 +2,8 @@ using Plots
 using Plots.PlotMeasures
 using LaTeXStrings
 
-function load_surfaces(file, L)
-    surfaces = zeros(Float64, L, L)
+function load_surfaces(file, Lx, Ly)
+    surfaces = zeros(Float64, Lx, Ly)
     n_surfaces = 0
 
     open(file) do f
@@ -25,18 +25,21 @@ function load_surfaces(file, L)
     return surfaces ./ n_surfaces
 end
 
-function find_file(srcdir, L, dist, param)
+function find_file(srcdir, Lx, Ly, dist, param; lattice="", periodic=false)
     files = readdir(srcdir)
     for file in files
         m = match(pattern, file)
         if m === nothing
             continue
         end
-        L_ = parse(Int64, m.captures[1])
-        dist_ = String(m.captures[2])
-        param_ = parse(Float64, m.captures[4])
+        Lx_ = parse(Int64, m.captures[1])
+        Ly_ = m.captures[2] === nothing ? Lx_ : parse(Int64, m.captures[2])
+        lattice_ = m.captures[3]
+        periodic_ = m.captures[4] == "p"
+        dist_ = String(m.captures[5])
+        param_ = parse(Float64, m.captures[7])
 
-        if L == L_ && dist == dist_ && param == param_
+        if Lx == Lx_ && Ly == Ly_ && lattice == lattice_ && periodic == periodic_ && dist == dist_ && param == param_
             return joinpath(srcdir, file)
         end
     end
@@ -44,6 +47,7 @@ function find_file(srcdir, L, dist, param)
 end
 
 L = 100
+Ly = L
 dist = "Inverse"
 param = 15
 
@@ -56,9 +60,9 @@ else
 end
 
 outdir = "C:/Users/javgua/Desktop/TFM/outputs/isosurfaces/"
-pattern = r"^isosurfaces_L(\d+)_(\w+)\((\w)=([\d.]+)\).out$"
-file = find_file(outdir, L, dist, param)
-surfaces = load_surfaces(file, L)
+pattern = r"^isosurfaces_L(\d+)(?:x(\d+))?([th]?d?)(p?)_(\w+)\((\w)=([\d.]+)\).out$"
+file = find_file(outdir, L, Ly, dist, param)
+surfaces = load_surfaces(file, L, Ly)
 
 
 x_ticks = [1, L / 2, L]
@@ -59,6 +59,12 @@ def compile(**kwargs):
         "n_threads": kwargs.get("n_threads", DEFAULT_N_THREADS),
         "n_tries": kwargs.get("n_tries", DEFAULT_N_TRIES),
     }
+    # Rectangular lattices: Lx rows and length Ly, each defaulting to L.
+    # Periodic lattices wrap the Lx rows into a cylinder.
+    # The lattice is "square", "triangular" or "honeycomb".
+    for key in ("Lx", "Ly", "periodic", "lattice"):
+        if key in kwargs:
+            build_config[key] = kwargs[key]
 
     print("Building with config:", build_config)
     yaml_path = r"..\config.yaml"
@@ -1,8 +1,13 @@
 // This file is autogenerated from build.rs
+use crate::lattice::Lattice;
 use f256::f256;
 pub type Precision = f256;
-pub const L: usize = 35;
+pub const LX: usize = 35;
+pub const LY: usize = 35;
+pub const PERIODIC: bool = false;
+pub const LATTICE: Lattice = Lattice::Square;
+pub const DIAGONALS: bool = false;
 pub const N_THREADS: usize = 11;
 pub const N_TRIES: usize = 200;
-pub const N_RES: usize = (L-1)*(L-2)+L*(L-2) + 2*L;
-pub const N_UNK: usize = L * (L - 2);
+pub const N_RES: usize = LATTICE.n_bonds(LX, LY - 2);
+pub const N_UNK: usize = LX * (LY - 2);
@@ -0,0 +1,289 @@
+//! Spatially correlated disorder.
+//!
+//! A Gaussian random field with the chosen correlation function is generated by Fourier
+//! filtering white noise, and every bond takes the quantile of the target distribution at the
+//! normal probability of the field at its midpoint. The bonds keep the distribution of the
+//! uncorrelated network, but their values are now correlated over the correlation length.
+//!
+//! The field lives on a grid of half the lattice spacing, on which the midpoints of all bonds
+//! fall. It wraps along the rows with the lattice when this is periodic, and is otherwise twice
+//! as large as the network so that the wrap of the FFT does not correlate opposite edges.
+use crate::config::{LX, LY, PERIODIC};
+use crate::dists::{standard_normal, Distribution, Parametric};
+use crate::float::Float;
+use crate::lattice::{bonds, coords, displacement, Bond, Orientation};
+use crate::math;
+use rand::rngs::ThreadRng;
+use rustfft::num_complex::Complex64;
+use rustfft::{FftDirection, FftPlanner};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Correlation {
+    /// `C(r) = exp(-r / length)`
+    Exponential { length: f64 },
+    /// `C(r) = (1 + (r / length)^2)^(-exponent / 2)`, decaying as `r^(-exponent)`
+    PowerLaw { length: f64, exponent: f64 },
+}
+
+impl Correlation {
+    /// Covariance of the field between points a distance `r` apart, in lattice spacings.
+    pub fn covariance(self, r: f64) -> f64 {
+        match self {
+            Correlation::Exponential { length } => (-r / length).exp(),
+            Correlation::PowerLaw { length, exponent } => {
+                (1.0 + (r / length).powi(2)).powf(-exponent / 2.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Correlation::Exponential { length } => write!(f, "xi={:.3}", length),
+            Correlation::PowerLaw { length, exponent } => {
+                write!(f, "xi={:.3},a={:.3}", length, exponent)
+            }
+        }
+    }
+}
+
+/// Two-dimensional transform in place of a row-major grid of `rows x cols` points, without
+/// normalization.
+fn fft2(data: &mut [Complex64], rows: usize, cols: usize, direction: FftDirection) {
+    let mut planner = FftPlanner::new();
+    planner.plan_fft(cols, direction).process(data);
+
+    let column_fft = planner.plan_fft(rows, direction);
+    let mut column: Vec<Complex64> = vec![Complex64::default(); rows];
+    for x in 0..cols {
+        for (y, v) in column.iter_mut().enumerate() {
+            *v = data[y * cols + x];
+        }
+        column_fft.process(&mut column);
+        for (y, v) in column.iter().enumerate() {
+            data[y * cols + x] = *v;
+        }
+    }
+}
+
+/// Gaussian random field of zero mean and unit variance, on a periodic row-major grid of
+/// `rows x cols` points `spacing` lattice spacings apart.
+///
+/// White noise is filtered with the square root of the spectrum of `correlation`, the Fourier
+/// transform of the covariance wrapped on the grid (circulant embedding).
+pub fn gaussian_field(
+    rows: usize,
+    cols: usize,
+    spacing: f64,
+    correlation: Correlation,
+    rng: &mut ThreadRng,
+) -> Vec<f64> {
+    let n = rows * cols;
+    let wrapped = |i: usize, len: usize| i.min(len - i) as f64 * spacing;
+
+    let mut covariance: Vec<Complex64> = (0..n)
+        .map(|k| {
+            let r = wrapped(k / cols, rows).hypot(wrapped(k % cols, cols));
+            Complex64::new(correlation.covariance(r), 0.0)
+        })
+        .collect();
+
+    // The embedding may have small negative eigenvalues, which are dropped
+    fft2(&mut covariance, rows, cols, FftDirection::Forward);
+    let spectrum: Vec<f64> = covariance.into_iter().map(|c| c.re.max(0.0)).collect();
+    let variance = spectrum.iter().sum::<f64>() / n as f64;
+
+    let mut field: Vec<Complex64> = (0..n)
+        .map(|_| Complex64::new(standard_normal::<f64>(rng), 0.0))
+        .collect();
+    fft2(&mut field, rows, cols, FftDirection::Forward);
+    for (w, s) in field.iter_mut().zip(spectrum) {
+        *w *= s.sqrt();
+    }
+    fft2(&mut field, rows, cols, FftDirection::Inverse);
+
+    let norm = n as f64 * variance.sqrt();
+    field.into_iter().map(|c| c.re / norm).collect()
+}
+
+/// Rows and columns of the field grid.
+fn field_shape() -> (usize, usize) {
+    let rows = if PERIODIC { 2 * LX } else { 4 * LX };
+    (rows, 4 * (LY - 2))
+}
+
+/// Point of the field grid at the midpoint of `bond`. The two diagonals of a plaquette cross at
+/// its centre and share its point.
+fn midpoint(bond: Bond) -> (usize, usize) {
+    match bond {
+        Bond::Internal(a, b) => {
+            let (row, col) = coords(a);
+            let (d_row, d_col) = displacement(a, b);
+            let y = (2 * row as isize + d_row).rem_euclid(2 * LX as isize);
+            (y as usize, (2 * col as isize + 2 + d_col) as usize)
+        }
+        Bond::High(a) => (2 * coords(a).0, 1),
+        Bond::Low(a) => (2 * coords(a).0, 2 * LY - 3),
+    }
+}
+
+/// `base` drawn on a correlated landscape.
+pub struct Correlated<D> {
+    base: D,
+    correlation: Correlation,
+}
+
+impl<D> Correlated<D> {
+    pub fn with(base: D, correlation: Correlation) -> Self {
+        Correlated { base, correlation }
+    }
+}
+
+impl<D: fmt::Display> fmt::Display for Correlated<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_corr({})", self.base, self.correlation)
+    }
+}
+
+impl<T: Float, D: Parametric<T>> Parametric<T> for Correlated<D> {
+    /// The base distribution with parameter `param`, with exponential correlations over one
+    /// lattice spacing.
+    fn new<F: Into<f64>>(param: F) -> Correlated<D> {
+        Correlated::with(D::new(param), Correlation::Exponential { length: 1.0 })
+    }
+}
+
+impl<T: Float, D: Distribution<T>> Distribution<T> for Correlated<D> {
+    /// A single value has the distribution of the base one.
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.base.sample(rng)
+    }
+
+    fn pdf(&self, x: T) -> T {
+        self.base.pdf(x)
+    }
+
+    fn cdf(&self, x: T) -> T {
+        self.base.cdf(x)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.base.quantile(u)
+    }
+
+    fn sample_along(&self, orientation: Orientation, rng: &mut ThreadRng) -> T {
+        self.base.sample_along(orientation, rng)
+    }
+
+    fn quantile_along(&self, orientation: Orientation, u: T) -> T {
+        self.base.quantile_along(orientation, u)
+    }
+
+    fn fill(&self, rng: &mut ThreadRng, resist: &mut [T]) {
+        let (rows, cols) = field_shape();
+        let field = gaussian_field(rows, cols, 0.5, self.correlation, rng);
+
+        for (x, bond) in resist.iter_mut().zip(bonds()) {
+            let (row, col) = midpoint(bond);
+            let u = math::normal_cdf(field[row * cols + col]);
+            let u = T::new(u.clamp(f64::EPSILON, 1.0 - f64::EPSILON));
+            *x = self.base.quantile_along(bond.orientation(), u);
+        }
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        self.base.mean()
+    }
+
+    fn variance(&self) -> T {
+        self.base.variance()
+    }
+
+    fn mean_log(&self) -> T {
+        self.base.mean_log()
+    }
+
+    fn var_log(&self) -> T {
+        self.base.var_log()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DIAGONALS, LATTICE, N_RES};
+    use approx::assert_relative_eq;
+    use std::collections::HashSet;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_fft2() {
+        // A grid with a prime number of columns against the direct sum
+        let (rows, cols) = (6, 7);
+        let x: Vec<Complex64> = (0..rows * cols)
+            .map(|i| Complex64::new(i as f64, (i * i % 5) as f64))
+            .collect();
+        let mut actual = x.clone();
+        fft2(&mut actual, rows, cols, FftDirection::Forward);
+
+        for (k, &v) in actual.iter().enumerate() {
+            let expected = x
+                .iter()
+                .enumerate()
+                .fold(Complex64::default(), |acc, (j, &w)| {
+                    let (ky, kx, jy, jx) = (k / cols, k % cols, j / cols, j % cols);
+                    let t = (ky * jy) as f64 / rows as f64 + (kx * jx) as f64 / cols as f64;
+                    acc + w * Complex64::from_polar(1.0, -2.0 * PI * t)
+                });
+            assert_relative_eq!(v.re, expected.re, epsilon = 1e-9);
+            assert_relative_eq!(v.im, expected.im, epsilon = 1e-9);
+        }
+
+        fft2(&mut actual, rows, cols, FftDirection::Inverse);
+        for (&v, &w) in actual.iter().zip(x.iter()) {
+            assert_relative_eq!(v.re / (rows * cols) as f64, w.re, epsilon = 1e-9);
+            assert_relative_eq!(v.im / (rows * cols) as f64, w.im, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_field_covariance() {
+        let mut rng = rand::rng();
+        let (rows, cols, n_fields) = (32, 24, 100);
+        let correlation = Correlation::Exponential { length: 2.0 };
+
+        let (mut var, mut cov) = (0.0, 0.0);
+        for _ in 0..n_fields {
+            let field = gaussian_field(rows, cols, 1.0, correlation, &mut rng);
+            for y in 0..rows {
+                for x in 0..cols {
+                    let g = field[y * cols + x];
+                    var += g * g;
+                    cov += g * field[((y + 2) % rows) * cols + x];
+                }
+            }
+        }
+        let n = (n_fields * rows * cols) as f64;
+
+        assert_relative_eq!(var / n, 1.0, epsilon = 0.1);
+        assert_relative_eq!(cov / n, correlation.covariance(2.0), epsilon = 0.1);
+    }
+
+    #[test]
+    fn test_midpoints() {
+        // Every bond has its own point of the field grid, but for the two diagonals of a
+        // plaquette, which cross at its centre
+        let (rows, cols) = field_shape();
+        let points: HashSet<(usize, usize)> = bonds().map(midpoint).collect();
+        let n_crossings = if DIAGONALS {
+            LATTICE.link_bonds(LX).filter(|&(a, b)| a > b).count() * (LY - 3)
+        } else {
+            0
+        };
+        assert_eq!(points.len(), N_RES - n_crossings);
+        assert!(points.iter().all(|&(y, x)| y < rows && x < cols));
+    }
+}
@@ -0,0 +1,333 @@
+//! Simple-cubic resistor networks between two parallel bus planes.
+//!
+//! The nodes form an `l x l x l` cube indexed by `cidx`, with the drive along `z`: every node of
+//! the first plane is joined to the high bus and every node of the last one to the low bus, at
+//! the same potentials as in the 2D solver. If the lattice is periodic both transverse directions
+//! wrap around. The system is sparse and is solved by conjugate gradients, and the isosurface of
+//! the potential is extracted with the tetrahedral variant of marching cubes, which splits every
+//! cell into six tetrahedra around its main diagonal and so has no ambiguous cases.
+use crate::config::{Precision, DIAGONALS, LATTICE, N_TRIES, PERIODIC};
+use crate::dists::Distribution;
+use crate::float::Float;
+use crate::graph;
+use crate::io::{self, MeshFormat};
+use crate::lattice::{Bond, Lattice, Orientation};
+use crate::percolation;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Settings of a run of `compute_cubic`.
+pub struct CubicOptions {
+    /// Number of nodes along each side of the cube
+    pub size: usize,
+    pub isosurface_value: f64,
+    /// Relative residual at which the conjugate gradient stops
+    pub tolerance: f64,
+    /// Format of the isosurface meshes, or `None` not to export them
+    pub mesh_format: Option<MeshFormat>,
+}
+
+#[inline]
+pub fn cidx(l: usize, x: usize, y: usize, z: usize) -> usize {
+    x + l * (y + l * z)
+}
+
+/// Bonds of the cube, first along `x`, then along `y` and `z`, and finally those to the buses.
+pub fn bonds(l: usize) -> impl Iterator<Item = Bond> + Clone {
+    let n_transverse = if PERIODIC { l } else { l - 1 };
+    let nodes = move |nx: usize, ny: usize, nz: usize| {
+        (0..nz).flat_map(move |z| (0..ny).flat_map(move |y| (0..nx).map(move |x| (x, y, z))))
+    };
+
+    let along_x = nodes(n_transverse, l, l)
+        .map(move |(x, y, z)| Bond::Internal(cidx(l, x, y, z), cidx(l, (x + 1) % l, y, z)));
+    let along_y = nodes(l, n_transverse, l)
+        .map(move |(x, y, z)| Bond::Internal(cidx(l, x, y, z), cidx(l, x, (y + 1) % l, z)));
+    let along_z = nodes(l, l, l - 1)
+        .map(move |(x, y, z)| Bond::Internal(cidx(l, x, y, z), cidx(l, x, y, z + 1)));
+    let high = nodes(l, l, 1).map(move |(x, y, _)| Bond::High(cidx(l, x, y, 0)));
+    let low = nodes(l, l, 1).map(move |(x, y, _)| Bond::Low(cidx(l, x, y, l - 1)));
+
+    along_x.chain(along_y).chain(along_z).chain(high).chain(low)
+}
+
+/// Bonds along `z`, between the buses, are horizontal and those across it vertical.
+fn orientation(l: usize, bond: Bond) -> Orientation {
+    match bond {
+        Bond::Internal(p, q) if p / (l * l) == q / (l * l) => Orientation::Vertical,
+        _ => Orientation::Horizontal,
+    }
+}
+
+/// Potentials of the nodes of the cube with bond conductances `resist`, and the number of
+/// conjugate gradient iterations it took. Clusters joined to neither bus are left at zero
+/// potential.
+pub fn solve<T: Float>(l: usize, resist: &[T], tol: T) -> Result<(Vec<T>, usize), String> {
+    graph::solve(l * l * l, bonds(l), resist, tol)
+}
+
+/// Effective conductance between the bus planes, from the current entering through the high one.
+pub fn conductance<T: Float>(l: usize, resist: &[T], x: &[T]) -> T {
+    graph::conductance(bonds(l), resist, x)
+}
+
+/// Triangle mesh of an isosurface, in lattice units.
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normal(mesh: &Mesh, t: [usize; 3]) -> [f64; 3] {
+    let [a, b, c] = t.map(|v| mesh.vertices[v]);
+    cross(sub(b, a), sub(c, a))
+}
+
+impl Mesh {
+    pub fn area(&self) -> f64 {
+        self.triangles
+            .iter()
+            .map(|&t| {
+                let n = normal(self, t);
+                0.5 * (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt()
+            })
+            .sum()
+    }
+}
+
+/// Corners of a cell as offsets along `x`, `y` and `z`, numbered by the bits of their index.
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// The six tetrahedra of a cell, all sharing the diagonal from corner 0 to corner 7.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 2, 3, 7],
+    [0, 2, 6, 7],
+    [0, 4, 6, 7],
+    [0, 4, 5, 7],
+    [0, 1, 5, 7],
+];
+
+/// Isosurface of the potentials `x` at `value`, with its normals pointing to lower potentials.
+/// Cells with a corner that is not `active` are skipped.
+pub fn marching_tetrahedra<T: Float>(l: usize, x: &[T], value: T, active: &[bool]) -> Mesh {
+    let mut mesh = Mesh {
+        vertices: Vec::new(),
+        triangles: Vec::new(),
+    };
+    // Vertices already placed on each edge, given by the unwrapped positions of its ends
+    let mut edges: HashMap<([usize; 3], [usize; 3]), usize> = HashMap::new();
+    let n_cells = if PERIODIC { l } else { l - 1 };
+
+    for z in 0..l - 1 {
+        for y in 0..n_cells {
+            for cx in 0..n_cells {
+                let pos = CORNERS.map(|[dx, dy, dz]| [cx + dx, y + dy, z + dz]);
+                let idx = pos.map(|[px, py, pz]| cidx(l, px % l, py % l, pz));
+                if idx.iter().any(|&i| !active[i]) {
+                    continue;
+                }
+                let val = idx.map(|i| x[i]);
+
+                for tet in TETRAHEDRA {
+                    let (inside, outside): (Vec<usize>, Vec<usize>) =
+                        tet.into_iter().partition(|&c| val[c].ge(value));
+
+                    let mut vertex = |a: usize, b: usize| {
+                        let key = if pos[a] < pos[b] {
+                            (pos[a], pos[b])
+                        } else {
+                            (pos[b], pos[a])
+                        };
+                        *edges.entry(key).or_insert_with(|| {
+                            let t = ((value - val[a]) / (val[b] - val[a])).into_f64();
+                            let p = [0, 1, 2].map(|k| {
+                                pos[a][k] as f64 + t * (pos[b][k] as f64 - pos[a][k] as f64)
+                            });
+                            mesh.vertices.push(p);
+                            mesh.vertices.len() - 1
+                        })
+                    };
+
+                    let triangles = match (inside.as_slice(), outside.as_slice()) {
+                        (&[a], &[b, c, d]) | (&[b, c, d], &[a]) => {
+                            vec![[vertex(a, b), vertex(a, c), vertex(a, d)]]
+                        }
+                        (&[a, b], &[c, d]) => {
+                            let (ac, ad, bc, bd) =
+                                (vertex(a, c), vertex(a, d), vertex(b, c), vertex(b, d));
+                            vec![[ac, ad, bd], [ac, bd, bc]]
+                        }
+                        _ => continue,
+                    };
+
+                    // Orient the triangles from the inside corners towards the outside ones
+                    let centre = |corners: &[usize]| {
+                        let k = corners.len() as f64;
+                        [0, 1, 2]
+                            .map(|i| corners.iter().map(|&c| pos[c][i] as f64).sum::<f64>() / k)
+                    };
+                    let direction = sub(centre(&outside), centre(&inside));
+                    for t in triangles {
+                        let n = normal(&mesh, t);
+                        let dot = n[0] * direction[0] + n[1] * direction[1] + n[2] * direction[2];
+                        mesh.triangles
+                            .push(if dot < 0.0 { [t[0], t[2], t[1]] } else { t });
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+/// Size of the cube used in file names, `C{l}` followed by `p` if the lattice is periodic.
+fn size_tag(l: usize) -> String {
+    let periodic = if PERIODIC { "p" } else { "" };
+    format!("C{l}{periodic}")
+}
+
+/// Checks that a cube of `l` nodes per side can be built on the lattice of the configuration.
+pub fn check_cubic(l: usize) -> Result<(), String> {
+    if LATTICE != Lattice::Square || DIAGONALS {
+        return Err(String::from(
+            "Cubic networks are only built from the square lattice without diagonals.",
+        ));
+    }
+    if l < 3 {
+        return Err(String::from(
+            "The cube needs at least three nodes per side.",
+        ));
+    }
+    Ok(())
+}
+
+pub fn compute_cubic<D, P>(dist: D, outdir: P, options: CubicOptions)
+where
+    D: Distribution<Precision>,
+    P: AsRef<Path>,
+{
+    let l = options.size;
+
+    let outdir: &Path = outdir.as_ref();
+    let mut rng = rand::rng();
+    let tol = Precision::new(options.tolerance);
+    let value = Precision::new(options.isosurface_value);
+
+    for iter in 0..N_TRIES {
+        let resist: Vec<Precision> = bonds(l)
+            .map(|bond| dist.sample_along(orientation(l, bond), &mut rng))
+            .collect();
+        let (duration, result) = timeit!(solve(l, &resist, tol));
+
+        let (x, iters) = match result {
+            Ok(solution) => solution,
+            Err(e) => {
+                println!(
+                    "{}/{}  {:>5}  dist={}  FAIL - {}",
+                    iter + 1,
+                    N_TRIES,
+                    size_tag(l),
+                    dist,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let g = conductance(l, &resist, &x);
+        let active = percolation::connected(l * l * l, bonds(l), &resist, |_| false);
+        let mesh = marching_tetrahedra(l, &x, value, &active);
+        let area = mesh.area();
+
+        println!(
+            "{}/{}  {:>5}  dist={}  time={:>5.3}s  iters={}  G={:<9.3e}  area={:.3}",
+            iter + 1,
+            N_TRIES,
+            size_tag(l),
+            dist,
+            duration.as_secs_f64(),
+            iters,
+            g,
+            area
+        );
+
+        let out_file = outdir.join(format!("cubic_{}_{}.out", size_tag(l), dist));
+        io::append_line(&out_file, &format!("{} {}", g, area)).expect("Failed at saving results");
+
+        if let Some(format) = options.mesh_format {
+            let mesh_file = outdir.join(format!(
+                "mesh_{}_{}_{:04}.{}",
+                size_tag(l),
+                dist,
+                iter,
+                format.extension()
+            ));
+            let header = vec![
+                format!("l={l}"),
+                format!("periodic={PERIODIC}"),
+                format!("dist={}", dist),
+                format!("value={}", options.isosurface_value),
+                format!("area={area}"),
+            ];
+            io::export_mesh(&mesh, &mesh_file, format, &header).expect("Failed at saving results");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_uniform_cube() {
+        let l = 4;
+        let resist: Vec<f64> = vec![1.0; bonds(l).count()];
+        let (x, _) = solve(l, &resist, 1e-12).unwrap();
+
+        // l * l parallel chains of l + 1 bonds
+        let expected = (l * l) as f64 / (l + 1) as f64;
+        assert_relative_eq!(conductance(l, &resist, &x), expected, epsilon = EPS);
+
+        // The zero potential lies halfway between the two middle planes
+        let mesh = marching_tetrahedra(l, &x, 0.0, &vec![true; l * l * l]);
+        let n_cells = if PERIODIC { l } else { l - 1 };
+        assert_relative_eq!(mesh.area(), (n_cells * n_cells) as f64, epsilon = EPS);
+        for v in mesh.vertices.iter() {
+            assert_relative_eq!(v[2], 1.5, epsilon = EPS);
+        }
+        for &t in mesh.triangles.iter() {
+            assert!(normal(&mesh, t)[2] > 0.0);
+        }
+
+        assert!(check_cubic(2).is_err());
+        assert_eq!(
+            check_cubic(l).is_ok(),
+            LATTICE == Lattice::Square && !DIAGONALS
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Fixed-current driving.
+//!
+//! Instead of holding the buses at `V_HGH` and `V_LOW`, a prescribed current is driven through
+//! the network: either from the high bus, whose potential is left floating, to the low bus, held
+//! at ground, or from current sources at chosen nodes to a grounded node, the buses then being
+//! left out. Grounding one potential removes the constant mode of the Laplacian, which is then
+//! positive definite and solved with conjugate gradients. The potential of the floating bus, or
+//! of the sources, is the voltage needed to carry the current.
+//!
+//! Sources are given in a specification file in the format of the electrodes, every line holding
+//! the current injected through its nodes, split evenly between them, and a line `ground` giving
+//! the grounded node. Currents not drained by other sources leave through the ground.
+//!
+//! ```text
+//! # current  nodes
+//!  1.0       17:0
+//! -0.5       0:32 34:32
+//!  ground    17:32
+//! ```
+use crate::config::{Precision, N_TRIES, N_UNK};
+use crate::dists::Distribution;
+use crate::electrodes::{parse_node_sets, read_spec};
+use crate::float::Float;
+use crate::io;
+use crate::lattice::{bonds, Bond};
+use crate::percolation;
+use crate::solver::{compute_isosurface, ExportMode};
+use crate::sparse::{conjugate_gradient, CsrMatrix};
+use std::path::Path;
+
+pub struct Sources {
+    /// Name of the specification, used in file names
+    pub name: String,
+    /// Current injected through each set of nodes
+    pub sources: Vec<(f64, Vec<usize>)>,
+    pub ground: usize,
+}
+
+enum SourceLine {
+    Current(f64),
+    Ground,
+}
+
+impl Sources {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Sources, String> {
+        let (name, text) = read_spec(path)?;
+        Sources::parse(&name, &text)
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Sources, String> {
+        let lines = parse_node_sets(text, |v| match v {
+            "ground" => Ok(SourceLine::Ground),
+            v => v
+                .parse::<f64>()
+                .map(SourceLine::Current)
+                .map_err(|e| format!("Invalid current {}: {}", v, e)),
+        })?;
+
+        let mut sources: Vec<(f64, Vec<usize>)> = Vec::new();
+        let mut ground: Vec<usize> = Vec::new();
+        for (line, nodes) in lines {
+            match line {
+                SourceLine::Current(current) => sources.push((current, nodes)),
+                SourceLine::Ground => ground.extend(nodes),
+            }
+        }
+
+        if sources.is_empty() {
+            return Err(String::from("No sources given"));
+        }
+        match ground[..] {
+            [ground] => Ok(Sources {
+                name: name.to_string(),
+                sources,
+                ground,
+            }),
+            _ => Err(String::from("Exactly one grounded node is needed")),
+        }
+    }
+}
+
+pub enum Drive {
+    /// Current entering through the high bus and leaving through the grounded low bus
+    Bus(f64),
+    /// Current sources at nodes, draining through the grounded node
+    Sources(Sources),
+}
+
+impl Drive {
+    /// Number of unknown potentials: the nodes, and the high bus when it floats.
+    fn n_unknowns(&self) -> usize {
+        match self {
+            Drive::Bus(_) => N_UNK + 1,
+            Drive::Sources(_) => N_UNK,
+        }
+    }
+
+    /// Suffix of the distribution in file names.
+    fn tag(&self) -> String {
+        match self {
+            Drive::Bus(current) => format!("cur(I={:.3})", current),
+            Drive::Sources(sources) => format!("src({})", sources.name),
+        }
+    }
+
+    /// Bonds of the network seen from the drive, with their conductances. The floating high bus
+    /// is node `N_UNK`, and the bonds to the grounded potential become `Bond::Low`.
+    fn bonds<T: Float>(&self, resist: &[T]) -> (Vec<Bond>, Vec<T>) {
+        bonds()
+            .zip(resist.iter().copied())
+            .filter_map(|(bond, g)| {
+                let bond = match (self, bond) {
+                    (Drive::Bus(_), Bond::High(a)) => Bond::Internal(a, N_UNK),
+                    (Drive::Bus(_), bond) => bond,
+                    (Drive::Sources(_), Bond::High(_) | Bond::Low(_)) => return None,
+                    (Drive::Sources(s), Bond::Internal(a, b)) if a == s.ground => Bond::Low(b),
+                    (Drive::Sources(s), Bond::Internal(a, b)) if b == s.ground => Bond::Low(a),
+                    (Drive::Sources(_), bond) => bond,
+                };
+                Some((bond, g))
+            })
+            .unzip()
+    }
+}
+
+/// Grounded Laplacian `a` and injected currents `b` of a sample, and its nodes left in the
+/// system.
+pub struct System<T: Float> {
+    pub a: CsrMatrix<T>,
+    pub b: Vec<T>,
+    pub active: Vec<bool>,
+}
+
+/// Assembles the grounded Laplacian of `resist` and the currents injected by `drive`. Clusters
+/// joined to no grounded potential are left out of the system, at zero potential, and a drive
+/// into one of them is an error since no current can flow.
+pub fn build_system<T: Float>(resist: &[T], drive: &Drive) -> Result<System<T>, String> {
+    let n = drive.n_unknowns();
+    let (bonds, resist) = drive.bonds(resist);
+    let ground = match drive {
+        Drive::Bus(_) => None,
+        Drive::Sources(sources) => Some(sources.ground),
+    };
+
+    let active = percolation::connected(n, bonds.iter().copied(), &resist, |p| Some(p) == ground);
+    let mut triplets: Vec<(usize, usize, T)> = (0..n)
+        .filter(|&p| !active[p] || Some(p) == ground)
+        .map(|p| (p, p, T::ONE))
+        .collect();
+
+    for (bond, &g) in bonds.iter().zip(resist.iter()) {
+        match *bond {
+            Bond::Internal(p, _) if !active[p] => continue,
+            Bond::Internal(p, q) => {
+                triplets.extend([(p, p, g), (q, q, g), (p, q, -g), (q, p, -g)]);
+            }
+            Bond::Low(p) if active[p] => triplets.push((p, p, g)),
+            _ => continue,
+        }
+    }
+
+    let mut b: Vec<T> = vec![T::ZERO; n];
+    match drive {
+        Drive::Bus(current) => {
+            if !active[N_UNK] {
+                return Err(String::from("No path joins the buses"));
+            }
+            b[N_UNK] = T::new(*current);
+        }
+        Drive::Sources(sources) => {
+            for (current, nodes) in sources.sources.iter() {
+                let share = T::new(*current / nodes.len() as f64);
+                for &p in nodes.iter() {
+                    if !active[p] {
+                        return Err(format!("Source at node {} is cut off from the ground", p));
+                    }
+                    b[p] = b[p] + share;
+                }
+            }
+        }
+    }
+
+    Ok(System {
+        a: CsrMatrix::from_triplets(n, triplets),
+        b,
+        active,
+    })
+}
+
+/// Solves the sample driven by `drive` and returns its system, the potentials of the nodes
+/// followed by that of the floating bus, and the number of conjugate gradient iterations.
+pub fn solve<T: Float>(
+    resist: &[T],
+    drive: &Drive,
+    tol: T,
+) -> Result<(System<T>, Vec<T>, usize), String> {
+    let system = build_system(resist, drive)?;
+    let mut x: Vec<T> = vec![T::ZERO; system.a.n];
+    let iters = conjugate_gradient(&system.a, &system.b, &mut x, tol, 10 * system.a.n)?;
+    Ok((system, x, iters))
+}
+
+/// Heat dissipated in the bonds, `sum g (x_a - x_b)^2`, which equals the injected power.
+pub fn joule_heat<T: Float>(resist: &[T], x: &[T], drive: &Drive) -> T {
+    let (bonds, resist) = drive.bonds(resist);
+    bonds
+        .iter()
+        .zip(resist.iter())
+        .map(|(bond, &g)| {
+            let dv = match *bond {
+                Bond::Internal(a, b) => x[a] - x[b],
+                Bond::High(a) | Bond::Low(a) => x[a],
+            };
+            g * dv * dv
+        })
+        .fold(T::ZERO, |a, b| a + b)
+}
+
+/// Potential of every source, averaged over its nodes, or of the floating bus.
+pub fn voltages<T: Float>(x: &[T], drive: &Drive) -> Vec<T> {
+    match drive {
+        Drive::Bus(_) => vec![x[N_UNK]],
+        Drive::Sources(sources) => sources
+            .sources
+            .iter()
+            .map(|(_, nodes)| {
+                nodes.iter().fold(T::ZERO, |a, &p| a + x[p]) / T::new(nodes.len() as f64)
+            })
+            .collect(),
+    }
+}
+
+pub struct CurrentOptions {
+    pub drive: Drive,
+    pub tolerance: f64,
+    pub export_mode: ExportMode,
+    pub isosurface_value: f64,
+}
+
+pub fn compute_current<D, P>(dist: D, outdir: P, options: CurrentOptions)
+where
+    D: Distribution<Precision>,
+    P: AsRef<Path>,
+{
+    let outdir: &Path = outdir.as_ref();
+    let mut rng = rand::rng();
+    let mut resist: Vec<Precision> = vec![Precision::ZERO; bonds().count()];
+    let tol = Precision::new(options.tolerance);
+    let drive = &options.drive;
+    let label = format!("{}_{}", dist, drive.tag());
+
+    for iter in 0..N_TRIES {
+        dist.fill(&mut rng, &mut resist);
+
+        let (duration, result) = timeit!(solve(&resist, drive, tol));
+
+        let (system, x, iters) = match result {
+            Ok(solution) => solution,
+            Err(e) => {
+                println!(
+                    "{}/{}  {:>5}  dist={}  FAIL - {}",
+                    iter + 1,
+                    N_TRIES,
+                    io::size_tag(),
+                    label,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let mut residual = vec![Precision::ZERO; system.a.n];
+        system.a.mul_vec(&x, &mut residual);
+        let residual: Vec<Precision> = residual
+            .iter()
+            .zip(system.b.iter())
+            .map(|(&r, &b)| r - b)
+            .collect();
+        let err2 = residual
+            .iter()
+            .fold(Precision::ZERO, |a, &r| a + r * r)
+            .sqrt();
+        let errinf = residual
+            .iter()
+            .map(|r| r.abs())
+            .fold(Precision::ZERO, |a, r| if a.lt(r) { r } else { a });
+
+        let voltages = voltages(&x, drive);
+        let heat = joule_heat(&resist, &x, drive);
+        let voltages_str = voltages
+            .iter()
+            .map(|v| format!("{:<9.3e}", v))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        println!(
+            "{}/{}  {:>5}  dist={}  time={:>5.3}s  iters={}  |err|2={:<9.3e}  V=[{}]  P={:<9.3e}",
+            iter + 1,
+            N_TRIES,
+            io::size_tag(),
+            label,
+            duration.as_secs_f64(),
+            iters,
+            err2,
+            voltages_str,
+            heat
+        );
+
+        let out_file = outdir.join(format!("current_{}_{}.out", io::size_tag(), label));
+        let line = voltages
+            .iter()
+            .chain([heat].iter())
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        io::append_line(&out_file, &line).expect("Failed at saving results");
+
+        let x = &x[..N_UNK];
+        let active = &system.active[..N_UNK];
+        let value = Precision::new(options.isosurface_value);
+        let surf_file = outdir.join(format!("isosurfaces_{}_{}.out", io::size_tag(), label));
+        match options.export_mode {
+            ExportMode::NoExport => continue,
+            ExportMode::ExportArrays => {
+                io::export_arrays(&label, outdir, &resist, x, err2, errinf, iter)
+                    .expect("Failed at saving results");
+            }
+            ExportMode::ExportIsoSurface => {
+                let surface = compute_isosurface(x, value, active);
+                io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+            }
+            ExportMode::ExportArraysAndIsoSurface => {
+                io::export_arrays(&label, outdir, &resist, x, err2, errinf, iter)
+                    .expect("Failed at saving results");
+                let surface = compute_isosurface(x, value, active);
+                io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LX, LY, N_RES};
+    use crate::lattice::{displacement, fidx};
+    use crate::starmesh;
+    use approx::assert_relative_eq;
+    use rand::Rng;
+
+    const EPS: f64 = 1e-8;
+
+    #[test]
+    fn test_bus_voltage() {
+        // The floating bus takes the voltage of the current through the effective conductance,
+        // and the injected power is all dissipated in the bonds
+        let mut rng = rand::rng();
+        let resist: Vec<f64> = (0..N_RES).map(|_| 0.1 + rng.random::<f64>()).collect();
+        let current = 2.5;
+        let drive = Drive::Bus(current);
+
+        let (_, x, _) = solve(&resist, &drive, 1e-14).unwrap();
+        let voltage = voltages(&x, &drive)[0];
+        let g = starmesh::effective_conductance(&resist);
+        assert_relative_eq!(voltage, current / g, epsilon = EPS);
+        assert_relative_eq!(
+            joule_heat(&resist, &x, &drive),
+            current * voltage,
+            epsilon = EPS
+        );
+    }
+
+    #[test]
+    fn test_sources() {
+        // A source on a chain of unit bonds along the first row, every other bond removed
+        let resist: Vec<f64> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b) == (0, 1) && a % LX == 0 => 1.0,
+                _ => 0.0,
+            })
+            .collect();
+        let spec = format!("1.5 0:0\nground 0:{}", LY - 3);
+        let drive = Drive::Sources(Sources::parse("chain", &spec).unwrap());
+
+        let (_, x, _) = solve(&resist, &drive, 1e-14).unwrap();
+        let expected = 1.5 * (LY - 3) as f64;
+        assert_relative_eq!(voltages(&x, &drive)[0], expected, epsilon = EPS);
+        assert_relative_eq!(x[fidx(0, 1)], expected - 1.5, epsilon = EPS);
+        assert_relative_eq!(x[fidx(1, 1)], 0.0, epsilon = EPS);
+
+        assert!(Sources::parse("bad", "1.0 0:0").is_err());
+        assert!(Sources::parse("bad", "1.0 0:0\nground 1:0 2:0").is_err());
+    }
+}
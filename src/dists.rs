@@ -1,121 +1,1598 @@
 use crate::float::Float;
+use crate::lattice::{bonds, Orientation};
+use crate::math;
 use rand::rngs::ThreadRng;
 use std::convert::Into;
 use std::fmt;
+use std::str::FromStr;
 
 pub trait Distribution<T>: fmt::Display
 where
     T: Float,
 {
-    fn new<F: Into<f64>>(param: F) -> Self;
     fn sample(&self, rng: &mut ThreadRng) -> T;
 
+    /// Probability density at `x`, leaving out the point masses of discrete values.
+    #[allow(unused)]
+    fn pdf(&self, x: T) -> T;
+
+    /// Cumulative distribution function, the fraction of the samples at or below `x`.
+    #[allow(unused)]
+    fn cdf(&self, x: T) -> T;
+
+    /// Value below which a fraction `u` of the samples fall, the inverse of the cumulative
+    /// distribution function.
+    fn quantile(&self, u: T) -> T;
+
+    /// Draws the value of a bond with the given orientation, which only anisotropic
+    /// distributions tell apart.
+    fn sample_along(&self, _orientation: Orientation, rng: &mut ThreadRng) -> T {
+        self.sample(rng)
+    }
+
+    /// Quantile of the values of the bonds with the given orientation.
+    fn quantile_along(&self, _orientation: Orientation, u: T) -> T {
+        self.quantile(u)
+    }
+
+    /// Draws the values of the bonds of the network into `resist`, independently unless the
+    /// distribution correlates them.
+    fn fill(&self, rng: &mut ThreadRng, resist: &mut [T]) {
+        for (x, bond) in resist.iter_mut().zip(bonds()) {
+            *x = self.sample_along(bond.orientation(), rng);
+        }
+    }
+
     #[allow(unused)]
     fn mean(&self) -> T;
+
+    #[allow(unused)]
+    fn variance(&self) -> T;
+
+    /// Mean of the logarithm of the values, the logarithm of their typical value.
+    #[allow(unused)]
+    fn mean_log(&self) -> T;
+
+    /// Variance of the logarithm of the values, the strength of the disorder.
+    #[allow(unused)]
+    fn var_log(&self) -> T;
+}
+
+/// Families of a single parameter, built from it alone as the `--dist` and `--param` options
+/// name them. Distributions built from data, an expression or an image have their own
+/// constructors instead.
+pub trait Parametric<T: Float>: Distribution<T> {
+    fn new<F: Into<f64>>(param: F) -> Self;
+}
+
+/// Variance of a mixture taking the first of two distributions, of the given means and
+/// variances, with probability `weight`.
+fn mixture_variance<T: Float>(weight: T, first: (T, T), second: (T, T)) -> T {
+    let ((m1, v1), (m2, v2)) = (first, second);
+    let mean = weight * m1 + (T::ONE - weight) * m2;
+    weight * (v1 + m1 * m1) + (T::ONE - weight) * (v2 + m2 * m2) - mean * mean
+}
+
+/// Mean and variance of the logarithm of a value uniform on `[low, high]`, through the
+/// antiderivatives of `ln x` and `ln^2 x`.
+pub fn uniform_log_moments<T: Float>(low: T, high: T) -> (T, T) {
+    if high == low {
+        return (low.ln(), T::ZERO);
+    }
+    let first = |x: T| {
+        if x == T::ZERO {
+            T::ZERO
+        } else {
+            x * (x.ln() - T::ONE)
+        }
+    };
+    let second = |x: T| {
+        if x == T::ZERO {
+            T::ZERO
+        } else {
+            let l = x.ln();
+            x * (l * l - T::TWO * l + T::TWO)
+        }
+    };
+    let mean = (first(high) - first(low)) / (high - low);
+    let square = (second(high) - second(low)) / (high - low);
+    (mean, square - mean * mean)
+}
+
+#[allow(unused)]
+pub struct InverseDist<T>
+where
+    T: Float,
+{
+    param: T,
+}
+
+impl<T: Float> fmt::Display for InverseDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Inverse(a={})", Param(self.param))
+    }
+}
+
+impl<T: Float> Parametric<T> for InverseDist<T> {
+    fn new<F: Into<f64>>(param: F) -> InverseDist<T> {
+        InverseDist {
+            param: T::new(param.into()),
+        }
+    }
+}
+
+impl<T: Float> Distribution<T> for InverseDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        (self.param * T::random(rng)).exp()
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x.lt(T::ONE) || self.param.exp().lt(x) {
+            T::ZERO
+        } else {
+            T::ONE / (self.param * x)
+        }
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if T::ONE.ge(x) {
+            T::ZERO
+        } else if x.ge(self.param.exp()) {
+            T::ONE
+        } else {
+            x.ln() / self.param
+        }
+    }
+
+    fn quantile(&self, u: T) -> T {
+        (self.param * u).exp()
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        (self.param.exp() - T::ONE) / self.param
+    }
+
+    fn variance(&self) -> T {
+        let mean = self.mean();
+        ((T::TWO * self.param).exp() - T::ONE) / (T::TWO * self.param) - mean * mean
+    }
+
+    /// The logarithm of the values is uniform on `[0, param]`.
+    fn mean_log(&self) -> T {
+        self.param / T::TWO
+    }
+
+    fn var_log(&self) -> T {
+        self.param * self.param / T::new(12.0)
+    }
+}
+
+/// Weibull distribution of shape `shape` and scale `scale`.
+#[allow(unused)]
+pub struct WeibullDist<T>
+where
+    T: Float,
+{
+    shape: T,
+    scale: T,
+}
+
+impl<T: Float> WeibullDist<T> {
+    pub fn with<F: Into<f64>>(shape: F, scale: F) -> WeibullDist<T> {
+        WeibullDist {
+            shape: T::new(shape.into()),
+            scale: T::new(scale.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for WeibullDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scale == T::ONE {
+            write!(f, "Weibull(k={})", Param(self.shape))
+        } else {
+            write!(
+                f,
+                "Weibull(k={},lambda={})",
+                Param(self.shape),
+                Param(self.scale)
+            )
+        }
+    }
+}
+
+impl<T: Float> Parametric<T> for WeibullDist<T> {
+    /// Weibull distribution of shape `param` and unit scale.
+    fn new<F: Into<f64>>(param: F) -> WeibullDist<T> {
+        WeibullDist::with(param.into(), 1.0)
+    }
+}
+
+impl<T: Float> Distribution<T> for WeibullDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        let power = (x / self.scale).powf(self.shape);
+        self.shape / x * power * (-power).exp()
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        T::ONE - (-(x / self.scale).powf(self.shape)).exp()
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.scale * (T::NEG_ONE * (T::ONE - u).ln()).powf(T::ONE / self.shape)
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        self.scale * (T::ONE + T::ONE / self.shape).gamma()
+    }
+
+    fn variance(&self) -> T {
+        let g1 = (T::ONE + T::ONE / self.shape).gamma();
+        let g2 = (T::ONE + T::TWO / self.shape).gamma();
+        self.scale * self.scale * (g2 - g1 * g1)
+    }
+
+    /// The logarithm of the values follows a Gumbel distribution of minima.
+    fn mean_log(&self) -> T {
+        self.scale.ln() + math::digamma(T::ONE) / self.shape
+    }
+
+    fn var_log(&self) -> T {
+        T::PI * T::PI / (T::new(6.0) * self.shape * self.shape)
+    }
+}
+
+/// Parameter in the label of a distribution: with three decimals, or with as many as it takes
+/// to read the label back into the same distribution.
+pub struct Param<T: Float>(pub T);
+
+impl<T: Float> fmt::Display for Param<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = self.0.into_f64();
+        let short = format!("{:.3}", x);
+        if T::new(short.parse::<f64>().unwrap()) == self.0 {
+            write!(f, "{}", short)
+        } else {
+            write!(f, "{}", x)
+        }
+    }
+}
+
+/// Draws a standard normal variate in the precision `T`, by the polar form of the Box-Muller
+/// transform.
+pub fn standard_normal<T: Float>(rng: &mut ThreadRng) -> T {
+    loop {
+        let u = T::TWO * T::random(rng) - T::ONE;
+        let v = T::TWO * T::random(rng) - T::ONE;
+        let s = u * u + v * v;
+        if s > T::ZERO && s.lt(T::ONE) {
+            return u * (T::NEG_ONE * T::TWO * s.ln() / s).sqrt();
+        }
+    }
+}
+
+/// Lognormal distribution: the logarithm of the values is normal with mean `location` and
+/// standard deviation `scale`.
+#[allow(unused)]
+pub struct LogNormalDist<T>
+where
+    T: Float,
+{
+    location: T,
+    scale: T,
+}
+
+impl<T: Float> LogNormalDist<T> {
+    pub fn with<F: Into<f64>>(location: F, scale: F) -> LogNormalDist<T> {
+        LogNormalDist {
+            location: T::new(location.into()),
+            scale: T::new(scale.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for LogNormalDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.location == T::ZERO {
+            write!(f, "LogNormal(s={})", Param(self.scale))
+        } else {
+            write!(
+                f,
+                "LogNormal(m={},s={})",
+                Param(self.location),
+                Param(self.scale)
+            )
+        }
+    }
+}
+
+impl<T: Float> Parametric<T> for LogNormalDist<T> {
+    /// Lognormal distribution of scale `param` around the unit median.
+    fn new<F: Into<f64>>(param: F) -> LogNormalDist<T> {
+        LogNormalDist::with(0.0, param.into())
+    }
+}
+
+impl<T: Float> Distribution<T> for LogNormalDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        (self.location + self.scale * standard_normal::<T>(rng)).exp()
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        let z = (x.ln() - self.location) / self.scale;
+        (-z * z / T::TWO).exp() / (x * self.scale * (T::TWO * T::PI).sqrt())
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        math::normal_cdf((x.ln() - self.location) / self.scale)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        (self.location + self.scale * math::normal_quantile(u)).exp()
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        (self.location + self.scale * self.scale / T::TWO).exp()
+    }
+
+    fn variance(&self) -> T {
+        let s2 = self.scale * self.scale;
+        (s2.exp() - T::ONE) * (T::TWO * self.location + s2).exp()
+    }
+
+    fn mean_log(&self) -> T {
+        self.location
+    }
+
+    fn var_log(&self) -> T {
+        self.scale * self.scale
+    }
+}
+
+/// Pareto distribution: a power-law tail of exponent `alpha` above the minimum value `scale`.
+/// The mean diverges for `alpha <= 1`.
+#[allow(unused)]
+pub struct ParetoDist<T>
+where
+    T: Float,
+{
+    alpha: T,
+    scale: T,
+}
+
+impl<T: Float> ParetoDist<T> {
+    pub fn with<F: Into<f64>>(alpha: F, scale: F) -> ParetoDist<T> {
+        ParetoDist {
+            alpha: T::new(alpha.into()),
+            scale: T::new(scale.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for ParetoDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pareto(a={},xm={})",
+            Param(self.alpha),
+            Param(self.scale)
+        )
+    }
+}
+
+impl<T: Float> Parametric<T> for ParetoDist<T> {
+    /// Pareto distribution of exponent `param` above 1.
+    fn new<F: Into<f64>>(param: F) -> ParetoDist<T> {
+        ParetoDist::with(param.into(), 1.0)
+    }
+}
+
+impl<T: Float> Distribution<T> for ParetoDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x.lt(self.scale) {
+            return T::ZERO;
+        }
+        self.alpha / x * (self.scale / x).powf(self.alpha)
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if self.scale.ge(x) {
+            return T::ZERO;
+        }
+        T::ONE - (self.scale / x).powf(self.alpha)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.scale * (T::ONE - u).powf(T::NEG_ONE / self.alpha)
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        if T::ONE.lt(self.alpha) {
+            self.alpha * self.scale / (self.alpha - T::ONE)
+        } else {
+            T::ONE / T::ZERO
+        }
+    }
+
+    /// Diverges for `alpha <= 2`.
+    fn variance(&self) -> T {
+        if T::TWO.lt(self.alpha) {
+            let a1 = self.alpha - T::ONE;
+            self.scale * self.scale * self.alpha / (a1 * a1 * (self.alpha - T::TWO))
+        } else {
+            T::ONE / T::ZERO
+        }
+    }
+
+    /// The logarithm of the values over `scale` is exponential of rate `alpha`.
+    fn mean_log(&self) -> T {
+        self.scale.ln() + T::ONE / self.alpha
+    }
+
+    fn var_log(&self) -> T {
+        T::ONE / (self.alpha * self.alpha)
+    }
+}
+
+/// Uniform distribution on `[low, high]`.
+#[allow(unused)]
+pub struct UniformDist<T>
+where
+    T: Float,
+{
+    low: T,
+    high: T,
+}
+
+impl<T: Float> UniformDist<T> {
+    pub fn with<F: Into<f64>>(low: F, high: F) -> UniformDist<T> {
+        UniformDist {
+            low: T::new(low.into()),
+            high: T::new(high.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for UniformDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Uniform(a={},b={})", Param(self.low), Param(self.high))
+    }
+}
+
+impl<T: Float> Parametric<T> for UniformDist<T> {
+    /// Uniform distribution on `[0, param]`.
+    fn new<F: Into<f64>>(param: F) -> UniformDist<T> {
+        UniformDist::with(0.0, param.into())
+    }
+}
+
+impl<T: Float> Distribution<T> for UniformDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x.lt(self.low) || self.high.lt(x) {
+            T::ZERO
+        } else {
+            T::ONE / (self.high - self.low)
+        }
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if self.low.ge(x) {
+            T::ZERO
+        } else if x.ge(self.high) {
+            T::ONE
+        } else {
+            (x - self.low) / (self.high - self.low)
+        }
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.low + (self.high - self.low) * u
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        (self.low + self.high) / T::TWO
+    }
+
+    fn variance(&self) -> T {
+        let width = self.high - self.low;
+        width * width / T::new(12.0)
+    }
+
+    fn mean_log(&self) -> T {
+        uniform_log_moments(self.low, self.high).0
+    }
+
+    fn var_log(&self) -> T {
+        uniform_log_moments(self.low, self.high).1
+    }
+}
+
+#[allow(unused)]
+pub struct ExponentialDist<T>
+where
+    T: Float,
+{
+    rate: T,
+}
+
+impl<T: Float> fmt::Display for ExponentialDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Exponential(lambda={})", Param(self.rate))
+    }
+}
+
+impl<T: Float> Parametric<T> for ExponentialDist<T> {
+    fn new<F: Into<f64>>(param: F) -> ExponentialDist<T> {
+        ExponentialDist {
+            rate: T::new(param.into()),
+        }
+    }
+}
+
+impl<T: Float> Distribution<T> for ExponentialDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x < T::ZERO {
+            return T::ZERO;
+        }
+        self.rate * (-self.rate * x).exp()
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        T::ONE - (-self.rate * x).exp()
+    }
+
+    fn quantile(&self, u: T) -> T {
+        T::NEG_ONE * (T::ONE - u).ln() / self.rate
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        T::ONE / self.rate
+    }
+
+    fn variance(&self) -> T {
+        T::ONE / (self.rate * self.rate)
+    }
+
+    fn mean_log(&self) -> T {
+        math::digamma(T::ONE) - self.rate.ln()
+    }
+
+    fn var_log(&self) -> T {
+        T::PI * T::PI / T::new(6.0)
+    }
+}
+
+/// Gamma distribution of shape `shape` and scale `scale`.
+#[allow(unused)]
+pub struct GammaDist<T>
+where
+    T: Float,
+{
+    shape: T,
+    scale: T,
+}
+
+impl<T: Float> GammaDist<T> {
+    pub fn with<F: Into<f64>>(shape: F, scale: F) -> GammaDist<T> {
+        GammaDist {
+            shape: T::new(shape.into()),
+            scale: T::new(scale.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for GammaDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gamma(k={},t={})", Param(self.shape), Param(self.scale))
+    }
+}
+
+impl<T: Float> Parametric<T> for GammaDist<T> {
+    /// Gamma distribution of shape `param` and unit scale.
+    fn new<F: Into<f64>>(param: F) -> GammaDist<T> {
+        GammaDist::with(param.into(), 1.0)
+    }
+}
+
+impl<T: Float> Distribution<T> for GammaDist<T> {
+    /// Marsaglia and Tsang's method, boosting shapes below 1 by a power of a uniform variate.
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        let boosted = self.shape.lt(T::ONE);
+        let shape = if boosted {
+            self.shape + T::ONE
+        } else {
+            self.shape
+        };
+        let d = shape - T::ONE / T::new(3.0);
+        let c = T::ONE / (T::new(9.0) * d).sqrt();
+
+        loop {
+            let z = standard_normal::<T>(rng);
+            let v = T::ONE + c * z;
+            if v <= T::ZERO {
+                continue;
+            }
+            let v = v * v * v;
+            let u = T::random(rng);
+            // The squeeze accepts most draws without the logarithms. Both tests compare against
+            // zero, since f256 orders two negative numbers the wrong way round.
+            let z2 = z * z;
+            if T::ONE - T::new(0.0331) * z2 * z2 - u > T::ZERO
+                || z2 / T::TWO + d - d * v + d * v.ln() - u.ln() > T::ZERO
+            {
+                let x = if boosted {
+                    d * v * T::random(rng).powf(T::ONE / self.shape)
+                } else {
+                    d * v
+                };
+                return x * self.scale;
+            }
+        }
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x <= T::ZERO {
+            return T::ZERO;
+        }
+        let z = x / self.scale;
+        ((self.shape - T::ONE) * z.ln() - z - math::ln_gamma(self.shape)).exp() / self.scale
+    }
+
+    fn cdf(&self, x: T) -> T {
+        math::regularized_gamma(self.shape, x / self.scale)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        math::gamma_quantile(self.shape, u) * self.scale
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        self.shape * self.scale
+    }
+
+    fn variance(&self) -> T {
+        self.shape * self.scale * self.scale
+    }
+
+    fn mean_log(&self) -> T {
+        math::digamma(self.shape) + self.scale.ln()
+    }
+
+    fn var_log(&self) -> T {
+        math::trigamma(self.shape)
+    }
+}
+
+/// Log-uniform distribution on `[low, high]`: the logarithm of the values is uniform.
+#[allow(unused)]
+pub struct LogUniformDist<T>
+where
+    T: Float,
+{
+    low: T,
+    high: T,
+}
+
+impl<T: Float> LogUniformDist<T> {
+    pub fn with<F: Into<f64>>(low: F, high: F) -> LogUniformDist<T> {
+        LogUniformDist {
+            low: T::new(low.into()),
+            high: T::new(high.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for LogUniformDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LogUniform(lo={},hi={})",
+            Param(self.low),
+            Param(self.high)
+        )
+    }
+}
+
+impl<T: Float> Parametric<T> for LogUniformDist<T> {
+    /// Log-uniform distribution over `param` decades above 1.
+    fn new<F: Into<f64>>(param: F) -> LogUniformDist<T> {
+        LogUniformDist::with(1.0, 10f64.powf(param.into()))
+    }
+}
+
+impl<T: Float> Distribution<T> for LogUniformDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x.lt(self.low) || self.high.lt(x) {
+            T::ZERO
+        } else {
+            T::ONE / (x * (self.high / self.low).ln())
+        }
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if self.low.ge(x) {
+            T::ZERO
+        } else if x.ge(self.high) {
+            T::ONE
+        } else {
+            (x / self.low).ln() / (self.high / self.low).ln()
+        }
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.low * (self.high / self.low).powf(u)
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        (self.high - self.low) / (self.high / self.low).ln()
+    }
+
+    fn variance(&self) -> T {
+        let mean = self.mean();
+        (self.high * self.high - self.low * self.low) / (T::TWO * (self.high / self.low).ln())
+            - mean * mean
+    }
+
+    fn mean_log(&self) -> T {
+        (self.low.ln() + self.high.ln()) / T::TWO
+    }
+
+    fn var_log(&self) -> T {
+        let width = (self.high / self.low).ln();
+        width * width / T::new(12.0)
+    }
+}
+
+/// Power law `x^(-alpha)` truncated to `[low, high]`, so that it has all its moments for any
+/// exponent.
+#[allow(unused)]
+pub struct TruncatedPowerLawDist<T>
+where
+    T: Float,
+{
+    alpha: T,
+    low: T,
+    high: T,
+}
+
+impl<T: Float> TruncatedPowerLawDist<T> {
+    pub fn with<F: Into<f64>>(alpha: F, low: F, high: F) -> TruncatedPowerLawDist<T> {
+        TruncatedPowerLawDist {
+            alpha: T::new(alpha.into()),
+            low: T::new(low.into()),
+            high: T::new(high.into()),
+        }
+    }
+
+    /// Integral of `x^(-alpha) x^moment` over the support.
+    fn moment_integral(&self, moment: T) -> T {
+        let exponent = T::ONE + moment - self.alpha;
+        if exponent == T::ZERO {
+            (self.high / self.low).ln()
+        } else {
+            (self.high.powf(exponent) - self.low.powf(exponent)) / exponent
+        }
+    }
+
+    /// Mean and variance of the logarithm of the values, which is exponential of rate
+    /// `alpha - 1` truncated to `[ln low, ln high]`.
+    fn log_moments(&self) -> (T, T) {
+        let width = (self.high / self.low).ln();
+        let rate = self.alpha - T::ONE;
+        if rate == T::ZERO {
+            return (self.low.ln() + width / T::TWO, width * width / T::new(12.0));
+        }
+        // Moments of the distance from the end where the density is highest
+        let rate = rate.abs();
+        let q = (-rate * width).exp();
+        let distance = T::ONE / rate - width * q / (T::ONE - q);
+        let variance = T::ONE / (rate * rate) - width * width * q / ((T::ONE - q) * (T::ONE - q));
+        if T::ONE.lt(self.alpha) {
+            (self.low.ln() + distance, variance)
+        } else {
+            (self.high.ln() - distance, variance)
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for TruncatedPowerLawDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "TruncPowerLaw(a={},lo={},hi={})",
+            Param(self.alpha),
+            Param(self.low),
+            Param(self.high)
+        )
+    }
+}
+
+impl<T: Float> Parametric<T> for TruncatedPowerLawDist<T> {
+    /// Power law of exponent `param` between 1 and 1000.
+    fn new<F: Into<f64>>(param: F) -> TruncatedPowerLawDist<T> {
+        TruncatedPowerLawDist::with(param.into(), 1.0, 1000.0)
+    }
+}
+
+impl<T: Float> Distribution<T> for TruncatedPowerLawDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.quantile(T::random(rng))
+    }
+
+    fn pdf(&self, x: T) -> T {
+        if x.lt(self.low) || self.high.lt(x) {
+            T::ZERO
+        } else {
+            x.powf(-self.alpha) / self.moment_integral(T::ZERO)
+        }
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if self.low.ge(x) {
+            return T::ZERO;
+        }
+        if x.ge(self.high) {
+            return T::ONE;
+        }
+        let exponent = T::ONE - self.alpha;
+        if exponent == T::ZERO {
+            (x / self.low).ln() / (self.high / self.low).ln()
+        } else {
+            let lo = self.low.powf(exponent);
+            (x.powf(exponent) - lo) / (self.high.powf(exponent) - lo)
+        }
+    }
+
+    fn quantile(&self, u: T) -> T {
+        let exponent = T::ONE - self.alpha;
+        if exponent == T::ZERO {
+            self.low * (self.high / self.low).powf(u)
+        } else {
+            let (lo, hi) = (self.low.powf(exponent), self.high.powf(exponent));
+            (lo + (hi - lo) * u).powf(T::ONE / exponent)
+        }
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        self.moment_integral(T::ONE) / self.moment_integral(T::ZERO)
+    }
+
+    fn variance(&self) -> T {
+        let mean = self.mean();
+        self.moment_integral(T::TWO) / self.moment_integral(T::ZERO) - mean * mean
+    }
+
+    fn mean_log(&self) -> T {
+        self.log_moments().0
+    }
+
+    fn var_log(&self) -> T {
+        self.log_moments().1
+    }
 }
 
+/// Binary mixture: each bond takes the value `high` with probability `fraction` and `low`
+/// otherwise.
 #[allow(unused)]
-pub struct InverseDist<T>
+pub struct BinaryDist<T>
 where
     T: Float,
 {
-    param: T,
+    fraction: T,
+    low: T,
+    high: T,
 }
 
-impl<T: Float> fmt::Display for InverseDist<T> {
+impl<T: Float> BinaryDist<T> {
+    pub fn with<F: Into<f64>>(fraction: F, low: F, high: F) -> BinaryDist<T> {
+        BinaryDist {
+            fraction: T::new(fraction.into()),
+            low: T::new(low.into()),
+            high: T::new(high.into()),
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for BinaryDist<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Inverse(a={:.3})", self.param)
+        write!(
+            f,
+            "Binary(p={},lo={},hi={})",
+            Param(self.fraction),
+            Param(self.low),
+            Param(self.high)
+        )
     }
 }
 
-impl<T: Float> Distribution<T> for InverseDist<T> {
-    fn new<F: Into<f64>>(param: F) -> InverseDist<T> {
-        InverseDist {
-            param: T::new(param.into()),
-        }
+impl<T: Float> Parametric<T> for BinaryDist<T> {
+    /// Bonds of unit value with probability `param`, and a thousand times weaker otherwise.
+    fn new<F: Into<f64>>(param: F) -> BinaryDist<T> {
+        BinaryDist::with(param.into(), 1e-3, 1.0)
     }
+}
 
+impl<T: Float> Distribution<T> for BinaryDist<T> {
     fn sample(&self, rng: &mut ThreadRng) -> T {
-        (self.param * T::random(rng)).exp()
+        if T::random(rng).lt(self.fraction) {
+            self.high
+        } else {
+            self.low
+        }
+    }
+
+    /// All the mass is in the two values.
+    fn pdf(&self, _x: T) -> T {
+        T::ZERO
+    }
+
+    fn cdf(&self, x: T) -> T {
+        if x.lt(self.low) {
+            T::ZERO
+        } else if x.lt(self.high) {
+            T::ONE - self.fraction
+        } else {
+            T::ONE
+        }
+    }
+
+    fn quantile(&self, u: T) -> T {
+        if u.lt(T::ONE - self.fraction) {
+            self.low
+        } else {
+            self.high
+        }
     }
 
     #[allow(unused)]
     fn mean(&self) -> T {
-        (self.param.exp() - T::ONE) / self.param
+        self.fraction * self.high + (T::ONE - self.fraction) * self.low
+    }
+
+    fn variance(&self) -> T {
+        let width = self.high - self.low;
+        self.fraction * (T::ONE - self.fraction) * width * width
+    }
+
+    fn mean_log(&self) -> T {
+        self.fraction * self.high.ln() + (T::ONE - self.fraction) * self.low.ln()
+    }
+
+    fn var_log(&self) -> T {
+        let width = (self.high / self.low).ln();
+        self.fraction * (T::ONE - self.fraction) * width * width
     }
 }
 
-#[allow(unused)]
-pub struct WeibullDist<T>
-where
-    T: Float,
-{
-    param: T,
+/// Bond dilution: with probability `fraction` a bond is replaced by a draw of `replacement`,
+/// or removed (zero conductance) if there is none.
+pub struct Diluted<T: Float, D: Distribution<T>> {
+    base: D,
+    fraction: T,
+    replacement: Option<Box<dyn Distribution<T>>>,
 }
 
-impl<T: Float> fmt::Display for WeibullDist<T> {
+impl<T: Float, D: Distribution<T>> Diluted<T, D> {
+    pub fn with(
+        base: D,
+        fraction: f64,
+        replacement: Option<Box<dyn Distribution<T>>>,
+    ) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(format!("Dilution fraction {} is not in [0, 1].", fraction));
+        }
+        Ok(Diluted {
+            base,
+            fraction: T::new(fraction),
+            replacement,
+        })
+    }
+}
+
+impl<T: Float, D: Distribution<T>> fmt::Display for Diluted<T, D> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Weibull(k={:.3})", self.param)
+        match &self.replacement {
+            Some(r) => write!(f, "{}_dil(p={:.3},{})", self.base, self.fraction, r),
+            None => write!(f, "{}_dil(p={:.3})", self.base, self.fraction),
+        }
     }
 }
 
-impl<T: Float> Distribution<T> for WeibullDist<T> {
-    fn new<F: Into<f64>>(param: F) -> WeibullDist<T> {
-        WeibullDist {
-            param: T::new(param.into()),
+impl<T: Float, D: Distribution<T>> Diluted<T, D> {
+    fn sample_diluted(&self, rng: &mut ThreadRng) -> T {
+        match &self.replacement {
+            Some(r) => r.sample(rng),
+            None => T::ZERO,
+        }
+    }
+
+    fn quantile_diluted(&self, u: T) -> T {
+        match &self.replacement {
+            Some(r) => r.quantile(u / self.fraction),
+            None => T::ZERO,
+        }
+    }
+}
+
+impl<T: Float, D: Parametric<T>> Parametric<T> for Diluted<T, D> {
+    /// The base distribution with parameter `param`, undiluted.
+    fn new<F: Into<f64>>(param: F) -> Diluted<T, D> {
+        Diluted {
+            base: D::new(param),
+            fraction: T::ZERO,
+            replacement: None,
         }
     }
+}
 
+impl<T: Float, D: Distribution<T>> Distribution<T> for Diluted<T, D> {
     fn sample(&self, rng: &mut ThreadRng) -> T {
-        (T::NEG_ONE * (T::ONE - T::random(rng)).ln()).powf(T::ONE / self.param)
+        match T::random(rng).ge(self.fraction) {
+            true => self.base.sample(rng),
+            false => self.sample_diluted(rng),
+        }
+    }
+
+    fn pdf(&self, x: T) -> T {
+        let replaced = self.replacement.as_ref().map_or(T::ZERO, |r| r.pdf(x));
+        (T::ONE - self.fraction) * self.base.pdf(x) + self.fraction * replaced
+    }
+
+    fn cdf(&self, x: T) -> T {
+        let replaced = match &self.replacement {
+            Some(r) => r.cdf(x),
+            None if x < T::ZERO => T::ZERO,
+            None => T::ONE,
+        };
+        (T::ONE - self.fraction) * self.base.cdf(x) + self.fraction * replaced
+    }
+
+    /// The lowest fraction of the quantiles is taken by the diluted bonds, so that a correlated
+    /// landscape dilutes its weakest regions together.
+    fn quantile(&self, u: T) -> T {
+        match u.ge(self.fraction) {
+            true => self
+                .base
+                .quantile((u - self.fraction) / (T::ONE - self.fraction)),
+            false => self.quantile_diluted(u),
+        }
+    }
+
+    fn sample_along(&self, orientation: Orientation, rng: &mut ThreadRng) -> T {
+        match T::random(rng).ge(self.fraction) {
+            true => self.base.sample_along(orientation, rng),
+            false => self.sample_diluted(rng),
+        }
+    }
+
+    fn quantile_along(&self, orientation: Orientation, u: T) -> T {
+        match u.ge(self.fraction) {
+            true => self
+                .base
+                .quantile_along(orientation, (u - self.fraction) / (T::ONE - self.fraction)),
+            false => self.quantile_diluted(u),
+        }
     }
 
     #[allow(unused)]
     fn mean(&self) -> T {
-        (T::ONE + T::ONE / self.param).gamma()
+        let removed = self.replacement.as_ref().map_or(T::ZERO, |r| r.mean());
+        (T::ONE - self.fraction) * self.base.mean() + self.fraction * removed
+    }
+
+    fn variance(&self) -> T {
+        let replaced = match &self.replacement {
+            Some(r) => (r.mean(), r.variance()),
+            None => (T::ZERO, T::ZERO),
+        };
+        let base = (self.base.mean(), self.base.variance());
+        mixture_variance(T::ONE - self.fraction, base, replaced)
+    }
+
+    /// Diverges to minus infinity when bonds are removed.
+    fn mean_log(&self) -> T {
+        if self.fraction == T::ZERO {
+            return self.base.mean_log();
+        }
+        match &self.replacement {
+            Some(r) => {
+                (T::ONE - self.fraction) * self.base.mean_log() + self.fraction * r.mean_log()
+            }
+            None => T::NEG_ONE / T::ZERO,
+        }
+    }
+
+    /// Diverges when bonds are removed.
+    fn var_log(&self) -> T {
+        if self.fraction == T::ZERO {
+            return self.base.var_log();
+        }
+        match &self.replacement {
+            Some(r) => {
+                let base = (self.base.mean_log(), self.base.var_log());
+                mixture_variance(T::ONE - self.fraction, base, (r.mean_log(), r.var_log()))
+            }
+            None => T::ONE / T::ZERO,
+        }
     }
 }
 
-#[allow(unused)]
-pub struct LogNormalDist<T>
-where
-    T: Float,
-{
-    param: T,
+/// Anisotropic disorder: horizontal bonds are drawn from `base`, vertical ones from `vertical`
+/// and diagonal ones from `diagonal`, or also from `base` for those missing.
+pub struct Anisotropic<T: Float, D: Distribution<T>> {
+    base: D,
+    vertical: Option<Box<dyn Distribution<T>>>,
+    diagonal: Option<Box<dyn Distribution<T>>>,
 }
 
-impl<T: Float> fmt::Display for LogNormalDist<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "LogNormal(s={:.3})", self.param)
+impl<T: Float, D: Distribution<T>> Anisotropic<T, D> {
+    pub fn with(
+        base: D,
+        vertical: Option<Box<dyn Distribution<T>>>,
+        diagonal: Option<Box<dyn Distribution<T>>>,
+    ) -> Self {
+        Anisotropic {
+            base,
+            vertical,
+            diagonal,
+        }
+    }
+
+    /// Distribution of the bonds of `orientation`, if it is not the base one.
+    fn along(&self, orientation: Orientation) -> Option<&dyn Distribution<T>> {
+        match orientation {
+            Orientation::Vertical => self.vertical.as_deref(),
+            Orientation::Diagonal => self.diagonal.as_deref(),
+            Orientation::Horizontal => None,
+        }
     }
 }
 
-impl<T: Float> Distribution<T> for LogNormalDist<T> {
-    fn new<F: Into<f64>>(param: F) -> LogNormalDist<T> {
-        LogNormalDist {
-            param: T::new(param.into()),
+impl<T: Float, D: Distribution<T>> fmt::Display for Anisotropic<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        if let Some(v) = &self.vertical {
+            write!(f, "_vert({})", v)?;
         }
+        if let Some(d) = &self.diagonal {
+            write!(f, "_diag({})", d)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Float, D: Parametric<T>> Parametric<T> for Anisotropic<T, D> {
+    /// The base distribution with parameter `param` in all orientations.
+    fn new<F: Into<f64>>(param: F) -> Anisotropic<T, D> {
+        Anisotropic::with(D::new(param), None, None)
     }
+}
 
+impl<T: Float, D: Distribution<T>> Distribution<T> for Anisotropic<T, D> {
+    /// A bond of unknown orientation is taken as horizontal.
     fn sample(&self, rng: &mut ThreadRng) -> T {
-        let w = T::random(rng);
-        let log = w.ln();
-        T::ONE / (w * (T::TWO * T::PI * self.param * self.param).sqrt())
-            * (T::NEG_ONE * log * log / (T::TWO * self.param * self.param)).exp()
+        self.base.sample(rng)
+    }
+
+    fn pdf(&self, x: T) -> T {
+        self.base.pdf(x)
+    }
+
+    fn cdf(&self, x: T) -> T {
+        self.base.cdf(x)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        self.base.quantile(u)
+    }
+
+    fn sample_along(&self, orientation: Orientation, rng: &mut ThreadRng) -> T {
+        match self.along(orientation) {
+            Some(d) => d.sample(rng),
+            None => self.base.sample_along(orientation, rng),
+        }
+    }
+
+    fn quantile_along(&self, orientation: Orientation, u: T) -> T {
+        match self.along(orientation) {
+            Some(d) => d.quantile(u),
+            None => self.base.quantile_along(orientation, u),
+        }
     }
 
     #[allow(unused)]
     fn mean(&self) -> T {
-        let s = self.param;
-        (s * (s * s / T::TWO)).exp() / s.abs()
-            - (s * (s * s / T::TWO).exp() * (s / T::TWO.sqrt()).erf()) / s.abs()
+        self.base.mean()
+    }
+
+    fn variance(&self) -> T {
+        self.base.variance()
+    }
+
+    fn mean_log(&self) -> T {
+        self.base.mean_log()
+    }
+
+    fn var_log(&self) -> T {
+        self.base.var_log()
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum ValidDists<T: Float> {
     InverseDist(InverseDist<T>),
     WeibullDist(WeibullDist<T>),
     LogNormalDist(LogNormalDist<T>),
+    ParetoDist(ParetoDist<T>),
+    UniformDist(UniformDist<T>),
+    ExponentialDist(ExponentialDist<T>),
+    GammaDist(GammaDist<T>),
+    LogUniformDist(LogUniformDist<T>),
+    TruncatedPowerLawDist(TruncatedPowerLawDist<T>),
+    BinaryDist(BinaryDist<T>),
+}
+
+/// Values a parameter of a distribution may take.
+#[derive(Clone, Copy, Debug)]
+pub enum Domain {
+    Real,
+    Positive,
+    NonNegative,
+    Probability,
+}
+
+impl Domain {
+    fn contains(self, x: f64) -> bool {
+        x.is_finite()
+            && match self {
+                Domain::Real => true,
+                Domain::Positive => x > 0.0,
+                Domain::NonNegative => x >= 0.0,
+                Domain::Probability => (0.0..=1.0).contains(&x),
+            }
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Domain::Real => write!(f, "a finite number"),
+            Domain::Positive => write!(f, "positive"),
+            Domain::NonNegative => write!(f, "non-negative"),
+            Domain::Probability => write!(f, "between 0 and 1"),
+        }
+    }
+}
+
+/// Named parameter of a family of distributions.
+pub struct ParamSpec {
+    /// Name of the parameter in labels, followed by the aliases accepted in specs
+    pub names: &'static [&'static str],
+    /// Value taken when a spec leaves the parameter out, or `None` if it is required
+    pub default: Option<f64>,
+    pub domain: Domain,
+}
+
+impl ParamSpec {
+    fn check(&self, family: &str, value: f64) -> Result<f64, String> {
+        if self.domain.contains(value) {
+            Ok(value)
+        } else {
+            Err(format!(
+                "Parameter {} of {} must be {}, got {}.",
+                self.names[0], family, self.domain, value
+            ))
+        }
+    }
+}
+
+const fn param(names: &'static [&'static str], default: Option<f64>, domain: Domain) -> ParamSpec {
+    ParamSpec {
+        names,
+        default,
+        domain,
+    }
+}
+
+/// Family of distributions known by name on the command line.
+pub struct Family {
+    pub name: &'static str,
+    pub params: &'static [ParamSpec],
+}
+
+/// Registry of the families of distributions, with their parameters in the order of the labels.
+pub const FAMILIES: &[Family] = &[
+    Family {
+        name: "inverse",
+        params: &[param(&["a"], None, Domain::Positive)],
+    },
+    Family {
+        name: "weibull",
+        params: &[
+            param(&["k", "shape"], None, Domain::Positive),
+            param(&["lambda", "scale"], Some(1.0), Domain::Positive),
+        ],
+    },
+    Family {
+        name: "lognormal",
+        params: &[
+            param(&["m", "mu"], Some(0.0), Domain::Real),
+            param(&["s", "sigma"], None, Domain::Positive),
+        ],
+    },
+    Family {
+        name: "pareto",
+        params: &[
+            param(&["a", "alpha"], None, Domain::Positive),
+            param(&["xm", "scale"], Some(1.0), Domain::Positive),
+        ],
+    },
+    Family {
+        name: "uniform",
+        params: &[
+            param(&["a", "low"], Some(0.0), Domain::NonNegative),
+            param(&["b", "high"], None, Domain::Positive),
+        ],
+    },
+    Family {
+        name: "exponential",
+        params: &[param(&["lambda", "l", "rate"], None, Domain::Positive)],
+    },
+    Family {
+        name: "gamma",
+        params: &[
+            param(&["k", "shape"], None, Domain::Positive),
+            param(&["t", "theta", "scale"], Some(1.0), Domain::Positive),
+        ],
+    },
+    Family {
+        name: "loguniform",
+        params: &[
+            param(&["lo", "low"], Some(1.0), Domain::Positive),
+            param(&["hi", "high"], None, Domain::Positive),
+        ],
+    },
+    Family {
+        name: "truncpowerlaw",
+        params: &[
+            param(&["a", "alpha"], None, Domain::Real),
+            param(&["lo", "low"], Some(1.0), Domain::Positive),
+            param(&["hi", "high"], Some(1000.0), Domain::Positive),
+        ],
+    },
+    Family {
+        name: "binary",
+        params: &[
+            param(&["p"], None, Domain::Probability),
+            param(&["lo", "low"], Some(1e-3), Domain::NonNegative),
+            param(&["hi", "high"], Some(1.0), Domain::NonNegative),
+        ],
+    },
+];
+
+impl Family {
+    /// Family called `name`, in any case.
+    pub fn find(name: &str) -> Result<&'static Family, String> {
+        FAMILIES
+            .iter()
+            .find(|family| family.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let known: Vec<&str> = FAMILIES.iter().map(|family| family.name).collect();
+                format!(
+                    "Distribution {} not supported. Known distributions: {}.",
+                    name,
+                    known.join(", ")
+                )
+            })
+    }
+
+    /// Required parameter set by the one-parameter form `name:param`.
+    fn main_param(&self) -> &ParamSpec {
+        self.params.iter().find(|p| p.default.is_none()).unwrap()
+    }
+
+    /// Values of all the parameters, in the order of `params`, from `key=value` pairs separated
+    /// by commas.
+    fn values(&self, pairs: &str) -> Result<Vec<f64>, String> {
+        let mut values: Vec<Option<f64>> = vec![None; self.params.len()];
+        for pair in pairs.split(',') {
+            let (key, value) = pair
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| {
+                    format!(
+                        "Parameter {} of {} is not of the form key=value.",
+                        pair, self.name
+                    )
+                })?;
+            let i = self
+                .params
+                .iter()
+                .position(|p| p.names.contains(&key))
+                .ok_or_else(|| {
+                    let known: Vec<&str> = self.params.iter().map(|p| p.names[0]).collect();
+                    format!(
+                        "Distribution {} has no parameter {}. Parameters: {}.",
+                        self.name,
+                        key,
+                        known.join(", ")
+                    )
+                })?;
+            if values[i].is_some() {
+                return Err(format!("Parameter {} of {} given twice.", key, self.name));
+            }
+            let value = value
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid value {} of parameter {}: {}", value, key, e))?;
+            values[i] = Some(value);
+        }
+
+        self.params
+            .iter()
+            .zip(values)
+            .map(|(p, value)| {
+                let value = value.or(p.default).ok_or_else(|| {
+                    format!(
+                        "Distribution {} requires parameter {}.",
+                        self.name, p.names[0]
+                    )
+                })?;
+                p.check(self.name, value)
+            })
+            .collect()
+    }
+}
+
+impl<T: Float + 'static> ValidDists<T> {
+    /// Distribution of the one-parameter form of the family `name`.
+    pub fn from_name(name: &str, param: f64) -> Result<ValidDists<T>, String> {
+        let family = Family::find(name)?;
+        let param = family.main_param().check(family.name, param)?;
+        Ok(match family.name {
+            "inverse" => ValidDists::InverseDist(InverseDist::new(param)),
+            "weibull" => ValidDists::WeibullDist(WeibullDist::new(param)),
+            "lognormal" => ValidDists::LogNormalDist(LogNormalDist::new(param)),
+            "pareto" => ValidDists::ParetoDist(ParetoDist::new(param)),
+            "uniform" => ValidDists::UniformDist(UniformDist::new(param)),
+            "exponential" => ValidDists::ExponentialDist(ExponentialDist::new(param)),
+            "gamma" => ValidDists::GammaDist(GammaDist::new(param)),
+            "loguniform" => ValidDists::LogUniformDist(LogUniformDist::new(param)),
+            "truncpowerlaw" => ValidDists::TruncatedPowerLawDist(TruncatedPowerLawDist::new(param)),
+            "binary" => ValidDists::BinaryDist(BinaryDist::new(param)),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Distribution of the family `family` with the values of all its parameters.
+    fn from_values(family: &Family, v: &[f64]) -> Result<ValidDists<T>, String> {
+        let ordered = |lo: usize, hi: usize| {
+            if v[lo] < v[hi] {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Parameter {} of {} must be below {}, got {} and {}.",
+                    family.params[lo].names[0],
+                    family.name,
+                    family.params[hi].names[0],
+                    v[lo],
+                    v[hi]
+                ))
+            }
+        };
+
+        Ok(match family.name {
+            "inverse" => ValidDists::InverseDist(InverseDist::new(v[0])),
+            "weibull" => ValidDists::WeibullDist(WeibullDist::with(v[0], v[1])),
+            "lognormal" => ValidDists::LogNormalDist(LogNormalDist::with(v[0], v[1])),
+            "pareto" => ValidDists::ParetoDist(ParetoDist::with(v[0], v[1])),
+            "uniform" => {
+                ordered(0, 1)?;
+                ValidDists::UniformDist(UniformDist::with(v[0], v[1]))
+            }
+            "exponential" => ValidDists::ExponentialDist(ExponentialDist::new(v[0])),
+            "gamma" => ValidDists::GammaDist(GammaDist::with(v[0], v[1])),
+            "loguniform" => {
+                ordered(0, 1)?;
+                ValidDists::LogUniformDist(LogUniformDist::with(v[0], v[1]))
+            }
+            "truncpowerlaw" => {
+                ordered(1, 2)?;
+                ValidDists::TruncatedPowerLawDist(TruncatedPowerLawDist::with(v[0], v[1], v[2]))
+            }
+            "binary" => ValidDists::BinaryDist(BinaryDist::with(v[0], v[1], v[2])),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Parses a distribution given as `name:param`, with the other parameters of the family at
+    /// their defaults, or as `name(key=value,...)`, which also reads back labels such as
+    /// `Gamma(k=2.000,t=0.500)`.
+    pub fn from_spec(spec: &str) -> Result<ValidDists<T>, String> {
+        let spec = spec.trim();
+        if let Some((name, pairs)) = spec.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            let family = Family::find(name.trim())?;
+            return ValidDists::from_values(family, &family.values(pairs)?);
+        }
+
+        let (name, param) = spec.split_once(':').ok_or_else(|| {
+            format!(
+                "Distribution {} is not of the form name:param or name(key=value,...).",
+                spec
+            )
+        })?;
+        let param = param
+            .trim()
+            .parse::<f64>()
+            .map_err(|e| format!("Invalid parameter {}: {}", param, e))?;
+        ValidDists::from_name(name.trim(), param)
+    }
+
+    pub fn boxed(self) -> Box<dyn Distribution<T>> {
+        match self {
+            ValidDists::InverseDist(d) => Box::new(d),
+            ValidDists::WeibullDist(d) => Box::new(d),
+            ValidDists::LogNormalDist(d) => Box::new(d),
+            ValidDists::ParetoDist(d) => Box::new(d),
+            ValidDists::UniformDist(d) => Box::new(d),
+            ValidDists::ExponentialDist(d) => Box::new(d),
+            ValidDists::GammaDist(d) => Box::new(d),
+            ValidDists::LogUniformDist(d) => Box::new(d),
+            ValidDists::TruncatedPowerLawDist(d) => Box::new(d),
+            ValidDists::BinaryDist(d) => Box::new(d),
+        }
+    }
+}
+
+impl<T: Float + 'static> FromStr for ValidDists<T> {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<ValidDists<T>, String> {
+        ValidDists::from_spec(spec)
+    }
 }
 
 #[cfg(test)]
@@ -125,7 +1602,6 @@ mod tests {
     use approx::assert_relative_eq;
     use f256::f256;
     use plotters::prelude::*;
-    use rand;
     use std::path::Path;
 
     const EPS: f64 = 5e-2;
@@ -177,12 +1653,12 @@ mod tests {
             .draw_series(histogram_data.iter().enumerate().map(|(i, &count)| {
                 let x0 = x_min + i as f64 * bin_width;
                 let x1 = x_min + (i + 1) as f64 * bin_width;
-                Rectangle::new([(x0, 0), (x1, count as i32)], BLUE.filled())
+                Rectangle::new([(x0, 0), (x1, count)], BLUE.filled())
             }))
             .unwrap();
 
         let vertical_dashed_line = |x: f64, color: &RGBColor| {
-            let color = color.clone();
+            let color = *color;
             (0..y_max)
                 .step_by(10)
                 .map(move |y| PathElement::new(vec![(x, y), (x, y + 5)], color.stroke_width(2)))
@@ -202,23 +1678,21 @@ mod tests {
 
         chart_context
             .configure_series_labels()
-            .border_style(&BLACK)
-            .background_style(&WHITE.mix(0.8))
+            .border_style(BLACK)
+            .background_style(WHITE.mix(0.8))
             .draw()
             .unwrap();
     }
 
-    fn test_dist<D, T>(mut rng: &mut ThreadRng, params: &Vec<f64>, n_tries: usize)
+    fn test_dist<D, T>(rng: &mut ThreadRng, params: &[f64], n_tries: usize)
     where
-        D: Distribution<T>,
+        D: Parametric<T>,
         T: Float + PrecisionStr + 'static,
     {
-        for p in params.into_iter() {
+        for p in params.iter() {
             let dist = D::new(*p);
             println!("Testing {}", dist);
-            let data: Vec<f64> = (0..n_tries)
-                .map(|_| dist.sample(&mut rng).into_f64())
-                .collect();
+            let data: Vec<f64> = (0..n_tries).map(|_| dist.sample(rng).into_f64()).collect();
 
             let precision = T::precision_str();
             let file_name = format!("{}_{}.png", dist, precision);
@@ -229,6 +1703,350 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_dilution() {
+        let mut rng = rand::rng();
+        let n_tries: usize = 10000;
+
+        let dist: Diluted<f64, WeibullDist<f64>> =
+            Diluted::with(WeibullDist::new(2.0), 0.3, None).unwrap();
+        let removed = (0..n_tries)
+            .filter(|_| dist.sample(&mut rng) == 0.0)
+            .count();
+        assert_relative_eq!(removed as f64 / n_tries as f64, 0.3, epsilon = EPS);
+
+        let replacement: Box<dyn Distribution<f64>> = Box::new(InverseDist::new(3.0));
+        let dist: Diluted<f64, WeibullDist<f64>> =
+            Diluted::with(WeibullDist::new(2.0), 0.3, Some(replacement)).unwrap();
+        let actual = (0..n_tries).map(|_| dist.sample(&mut rng)).sum::<f64>() / n_tries as f64;
+        assert_relative_eq!(actual / dist.mean(), 1.0, epsilon = EPS);
+
+        assert!(Diluted::<f64, _>::with(WeibullDist::new(2.0), 1.5, None).is_err());
+    }
+
+    #[test]
+    fn test_anisotropy() {
+        let mut rng = rand::rng();
+        let n_tries: usize = 10000;
+
+        let vertical: Box<dyn Distribution<f64>> = Box::new(WeibullDist::new(1.0));
+        let diagonal: Box<dyn Distribution<f64>> = Box::new(InverseDist::new(1.0));
+        let dist: Anisotropic<f64, InverseDist<f64>> =
+            Anisotropic::with(InverseDist::new(3.0), Some(vertical), Some(diagonal));
+        assert_eq!(
+            dist.to_string(),
+            "Inverse(a=3.000)_vert(Weibull(k=1.000))_diag(Inverse(a=1.000))"
+        );
+
+        for (orientation, expected) in [
+            (Orientation::Horizontal, InverseDist::<f64>::new(3.0).mean()),
+            (Orientation::Vertical, WeibullDist::<f64>::new(1.0).mean()),
+            (Orientation::Diagonal, InverseDist::<f64>::new(1.0).mean()),
+        ] {
+            let actual = (0..n_tries)
+                .map(|_| dist.sample_along(orientation, &mut rng))
+                .sum::<f64>()
+                / n_tries as f64;
+            assert_relative_eq!(actual / expected, 1.0, epsilon = EPS);
+        }
+    }
+
+    /// Largest distance between the empirical distribution function of the logarithms of the
+    /// samples and the normal one they should follow (Kolmogorov-Smirnov statistic).
+    fn lognormal_ks<T: Float>(dist: &LogNormalDist<T>, rng: &mut ThreadRng, n: usize) -> f64 {
+        let (location, scale) = (dist.location.into_f64(), dist.scale.into_f64());
+        let mut z: Vec<f64> = (0..n)
+            .map(|_| (dist.sample(rng).into_f64().ln() - location) / scale)
+            .collect();
+        z.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        z.iter()
+            .enumerate()
+            .map(|(i, &z)| {
+                let cdf = math::normal_cdf(z);
+                (cdf - i as f64 / n as f64).max((i + 1) as f64 / n as f64 - cdf)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_lognormal() {
+        let mut rng = rand::rng();
+        let n: usize = 10000;
+        // Critical value of the Kolmogorov-Smirnov statistic at the 0.1% level
+        let critical = 1.95 / (n as f64).sqrt();
+
+        for (location, scale) in [(0.0, 1.0), (2.0, 0.5), (-1.0, 3.0)] {
+            let dist: LogNormalDist<f64> = LogNormalDist::with(location, scale);
+            assert!(lognormal_ks(&dist, &mut rng, n) < critical, "{}", dist);
+
+            let logs: Vec<f64> = (0..n).map(|_| dist.sample(&mut rng).ln()).collect();
+            let mean = logs.iter().sum::<f64>() / n as f64;
+            let var = logs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            assert!((mean - location).abs() < 4.0 * scale / (n as f64).sqrt());
+            assert_relative_eq!(var.sqrt() / scale, 1.0, epsilon = EPS);
+        }
+
+        let dist: LogNormalDist<f256> = LogNormalDist::with(0.5, 2.0);
+        assert_eq!(dist.to_string(), "LogNormal(m=0.500,s=2.000)");
+        assert!(lognormal_ks(&dist, &mut rng, 2000) < 1.95 / 2000f64.sqrt());
+    }
+
+    #[test]
+    fn test_specs() {
+        let label = |spec: &str| ValidDists::<f64>::from_spec(spec).map(|d| d.boxed().to_string());
+
+        // Named parameters in any order, with aliases and defaults
+        assert_eq!(
+            label("weibull(k=3,lambda=2)").unwrap(),
+            "Weibull(k=3.000,lambda=2.000)"
+        );
+        assert_eq!(
+            label(" Weibull( scale=2, shape=3 ) ").unwrap(),
+            "Weibull(k=3.000,lambda=2.000)"
+        );
+        assert_eq!(label("weibull(k=3)").unwrap(), "Weibull(k=3.000)");
+        assert_eq!(
+            label("lognormal(mu=1,sigma=0.5)").unwrap(),
+            "LogNormal(m=1.000,s=0.500)"
+        );
+        assert_eq!(
+            label("binary(p=0.4)").unwrap(),
+            "Binary(p=0.400,lo=0.001,hi=1.000)"
+        );
+        assert_eq!(label("weibull:3").unwrap(), "Weibull(k=3.000)");
+
+        for (spec, error) in [
+            ("cauchy(a=1)", "Distribution cauchy not supported."),
+            ("weibull", "is not of the form"),
+            ("weibull(3)", "is not of the form key=value"),
+            ("weibull(k=3,x=1)", "has no parameter x"),
+            ("weibull(k=3,shape=2)", "given twice"),
+            ("weibull(lambda=2)", "requires parameter k"),
+            ("weibull(k=three)", "Invalid value three"),
+            (
+                "weibull(k=-3)",
+                "Parameter k of weibull must be positive, got -3.",
+            ),
+            ("weibull:0", "must be positive"),
+            ("weibull(k=inf)", "must be positive"),
+            ("lognormal(m=nan,s=1)", "must be a finite number"),
+            ("binary(p=1.5)", "must be between 0 and 1"),
+            ("uniform(a=2,b=1)", "Parameter a of uniform must be below b"),
+            ("truncpowerlaw(a=2,lo=10,hi=5)", "must be below hi"),
+        ] {
+            let result = label(spec);
+            assert!(
+                matches!(&result, Err(e) if e.contains(error)),
+                "{spec}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_quantiles() {
+        // The mean is the integral of the quantile function over (0, 1)
+        let n: usize = 100000;
+        let integrate = |dist: &dyn Distribution<f64>| {
+            (0..n)
+                .map(|i| dist.quantile((i as f64 + 0.5) / n as f64))
+                .sum::<f64>()
+                / n as f64
+        };
+
+        let dist: InverseDist<f64> = InverseDist::new(5.0);
+        assert_relative_eq!(integrate(&dist) / dist.mean(), 1.0, epsilon = 1e-3);
+
+        let dist: WeibullDist<f64> = WeibullDist::new(2.0);
+        assert_relative_eq!(integrate(&dist) / dist.mean(), 1.0, epsilon = 1e-3);
+
+        let dist: LogNormalDist<f64> = LogNormalDist::with(1.0, 0.5);
+        assert_relative_eq!(integrate(&dist) / dist.mean(), 1.0, epsilon = 1e-3);
+
+        let replacement: Box<dyn Distribution<f64>> = Box::new(InverseDist::new(3.0));
+        let dist: Diluted<f64, WeibullDist<f64>> =
+            Diluted::with(WeibullDist::new(2.0), 0.3, Some(replacement)).unwrap();
+        assert_relative_eq!(integrate(&dist) / dist.mean(), 1.0, epsilon = 1e-3);
+
+        let dists: Vec<Box<dyn Distribution<f64>>> = vec![
+            Box::new(ParetoDist::with(4.0, 0.5)),
+            Box::new(UniformDist::with(0.5, 2.0)),
+            Box::new(ExponentialDist::new(3.0)),
+            Box::new(GammaDist::with(2.5, 0.5)),
+            Box::new(LogUniformDist::with(0.01, 100.0)),
+            Box::new(TruncatedPowerLawDist::with(1.0, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(2.0, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(3.5, 0.1, 10.0)),
+            Box::new(BinaryDist::with(0.25, 0.1, 2.0)),
+        ];
+        for dist in dists.iter() {
+            assert_relative_eq!(integrate(&**dist) / dist.mean(), 1.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_cdfs() {
+        // The distribution function inverts the quantile function, and the density is its slope
+        let dists: Vec<Box<dyn Distribution<f64>>> = vec![
+            Box::new(InverseDist::new(5.0)),
+            Box::new(WeibullDist::with(2.0, 1.5)),
+            Box::new(LogNormalDist::with(1.0, 0.5)),
+            Box::new(ParetoDist::with(4.0, 0.5)),
+            Box::new(UniformDist::with(0.5, 2.0)),
+            Box::new(ExponentialDist::new(3.0)),
+            Box::new(GammaDist::with(2.5, 0.5)),
+            Box::new(GammaDist::with(0.5, 2.0)),
+            Box::new(LogUniformDist::with(0.01, 100.0)),
+            Box::new(TruncatedPowerLawDist::with(1.0, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(3.5, 0.1, 10.0)),
+        ];
+        for dist in dists.iter() {
+            assert_eq!(dist.cdf(0.0), 0.0, "{}", dist);
+            assert_eq!(dist.cdf(1e6), 1.0, "{}", dist);
+            for u in [0.01, 0.2, 0.5, 0.8, 0.99] {
+                let x = dist.quantile(u);
+                assert_relative_eq!(dist.cdf(x), u, epsilon = 1e-6);
+                let h = 1e-5 * x;
+                let slope = (dist.cdf(x + h) - dist.cdf(x - h)) / (2.0 * h);
+                assert_relative_eq!(dist.pdf(x) / slope, 1.0, epsilon = 1e-4);
+            }
+        }
+
+        // The gamma and normal functions follow the precision of f256
+        let dists: Vec<Box<dyn Distribution<f256>>> = vec![
+            Box::new(LogNormalDist::with(1.0, 0.5)),
+            Box::new(GammaDist::with(2.5, 0.5)),
+            Box::new(GammaDist::with(0.5, 2.0)),
+        ];
+        for dist in dists.iter() {
+            for u in [1e-6, 0.2, 0.5, 0.99] {
+                let u = f256::from(u);
+                let x = dist.quantile(u);
+                assert!((dist.cdf(x) - u).abs() < f256::from(1e-40), "{}", dist);
+            }
+        }
+
+        let dist: BinaryDist<f64> = BinaryDist::with(0.25, 0.1, 2.0);
+        for (x, u) in [(0.05, 0.0), (0.1, 0.75), (1.0, 0.75), (2.0, 1.0)] {
+            assert_eq!(dist.cdf(x), u);
+            assert_eq!(dist.pdf(x), 0.0);
+        }
+
+        // Removed bonds are a point mass at zero
+        let base: WeibullDist<f64> = WeibullDist::new(2.0);
+        let dist: Diluted<f64, WeibullDist<f64>> =
+            Diluted::with(WeibullDist::new(2.0), 0.3, None).unwrap();
+        assert_eq!(dist.cdf(-1.0), 0.0);
+        assert_relative_eq!(dist.cdf(0.0), 0.3);
+        assert_relative_eq!(dist.cdf(0.5), 0.3 + 0.7 * base.cdf(0.5));
+        assert_relative_eq!(dist.pdf(0.5), 0.7 * base.pdf(0.5));
+
+        let dist: WeibullDist<f256> = WeibullDist::with(2.0, 1.5);
+        let u = f256::from(0.3);
+        assert!((dist.cdf(dist.quantile(u)) - u).abs() < f256::from(1e-40));
+    }
+
+    #[test]
+    fn test_moments() {
+        // Moments of the values and of their logarithms, against integrals of the quantile
+        // function over (0, 1)
+        let n: usize = 100000;
+        let integrate = |dist: &dyn Distribution<f64>, f: &dyn Fn(f64) -> f64| {
+            let values: Vec<f64> = (0..n)
+                .map(|i| f(dist.quantile((i as f64 + 0.5) / n as f64)))
+                .collect();
+            let mean = values.iter().sum::<f64>() / n as f64;
+            let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+            (mean, var)
+        };
+
+        let replacement: Box<dyn Distribution<f64>> = Box::new(InverseDist::new(3.0));
+        let dists: Vec<Box<dyn Distribution<f64>>> = vec![
+            Box::new(InverseDist::new(5.0)),
+            Box::new(WeibullDist::with(2.0, 1.5)),
+            Box::new(LogNormalDist::with(1.0, 0.5)),
+            Box::new(ParetoDist::with(8.0, 0.5)),
+            Box::new(UniformDist::with(0.5, 2.0)),
+            Box::new(UniformDist::new(2.0)),
+            Box::new(ExponentialDist::new(3.0)),
+            Box::new(GammaDist::with(2.5, 0.5)),
+            Box::new(GammaDist::with(0.5, 2.0)),
+            Box::new(LogUniformDist::with(0.01, 100.0)),
+            Box::new(TruncatedPowerLawDist::with(1.0, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(2.0, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(3.5, 0.1, 10.0)),
+            Box::new(TruncatedPowerLawDist::with(-1.0, 0.1, 10.0)),
+            Box::new(BinaryDist::with(0.25, 0.1, 2.0)),
+            Box::new(Diluted::with(WeibullDist::new(2.0), 0.3, Some(replacement)).unwrap()),
+        ];
+        for dist in dists.iter() {
+            let (_, var) = integrate(&**dist, &|x| x);
+            assert_relative_eq!(dist.variance() / var, 1.0, epsilon = 2e-3);
+            let (mean_log, var_log) = integrate(&**dist, &|x| x.ln());
+            assert_relative_eq!(dist.mean_log(), mean_log, epsilon = 1e-3);
+            assert_relative_eq!(dist.var_log() / var_log, 1.0, epsilon = 2e-3);
+        }
+
+        let dist: Diluted<f64, WeibullDist<f64>> =
+            Diluted::with(WeibullDist::new(2.0), 0.3, None).unwrap();
+        assert_eq!(dist.mean_log(), f64::NEG_INFINITY);
+        assert_eq!(dist.var_log(), f64::INFINITY);
+        let dist: ParetoDist<f64> = ParetoDist::new(1.5);
+        assert_eq!(dist.variance(), f64::INFINITY);
+
+        // The same moments in the other precisions
+        for label in [
+            "Inverse(a=3.000)",
+            "Weibull(k=3.000,lambda=2.000)",
+            "LogNormal(m=-0.500,s=0.750)",
+            "Pareto(a=4.000,xm=0.001)",
+            "Gamma(k=2.000,t=0.500)",
+            "TruncPowerLaw(a=2.500,lo=1.000,hi=1000.000)",
+        ] {
+            let dist = ValidDists::<f64>::from_spec(label).unwrap().boxed();
+            let dist256 = ValidDists::<f256>::from_spec(label).unwrap().boxed();
+            let x = dist.quantile(0.3);
+            let pairs = [
+                (dist.pdf(x), dist256.pdf(f256::from(x))),
+                (dist.cdf(x), dist256.cdf(f256::from(x))),
+                (dist.variance(), dist256.variance()),
+                (dist.mean_log(), dist256.mean_log()),
+                (dist.var_log(), dist256.var_log()),
+            ];
+            for (value, value256) in pairs {
+                assert_relative_eq!(value256.into_f64(), value, max_relative = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        // Labels are read back into the same distribution, exactly when they need more than
+        // three decimals
+        let labels = [
+            "Inverse(a=3.000)",
+            "Weibull(k=0.125)",
+            "LogNormal(s=1.000)",
+            "LogNormal(m=-0.500,s=0.7071067811865476)",
+            "Pareto(a=1.500,xm=0.001)",
+            "Uniform(a=0.100,b=2.000)",
+            "Exponential(lambda=0.3333333333333333)",
+            "Weibull(k=3.000,lambda=2.000)",
+            "Gamma(k=2.000,t=0.500)",
+            "LogUniform(lo=0.001,hi=1000.000)",
+            "TruncPowerLaw(a=2.500,lo=1.000,hi=1000.000)",
+            "Binary(p=0.300,lo=0.001,hi=1.000)",
+        ];
+        for label in labels {
+            let dist: ValidDists<f64> = label.parse().unwrap();
+            assert_eq!(dist.boxed().to_string(), label);
+            let dist = ValidDists::<f256>::from_spec(label).unwrap();
+            assert_eq!(dist.boxed().to_string(), label);
+        }
+
+        let dist = ValidDists::<f64>::from_spec("gamma:2").unwrap().boxed();
+        assert_eq!(dist.to_string(), "Gamma(k=2.000,t=1.000)");
+    }
+
     #[test]
     fn test_means() {
         let mut rng = rand::rng();
@@ -247,5 +2065,26 @@ mod tests {
         let params = vec![1.0, 0.5, 0.25];
         test_dist::<LogNormalDist<f64>, f64>(&mut rng, &params, n_tries);
         test_dist::<LogNormalDist<f256>, f256>(&mut rng, &params, n_tries);
+
+        let params = vec![3.0, 4.0, 6.0];
+        test_dist::<ParetoDist<f64>, f64>(&mut rng, &params, n_tries);
+
+        let params = vec![1.0, 2.0];
+        test_dist::<UniformDist<f64>, f64>(&mut rng, &params, n_tries);
+        test_dist::<ExponentialDist<f64>, f64>(&mut rng, &params, n_tries);
+        test_dist::<ExponentialDist<f256>, f256>(&mut rng, &params, n_tries);
+
+        let params = vec![0.5, 2.0, 5.0];
+        test_dist::<GammaDist<f64>, f64>(&mut rng, &params, n_tries);
+        test_dist::<GammaDist<f256>, f256>(&mut rng, &params, n_tries);
+
+        let params = vec![0.5, 1.0];
+        test_dist::<LogUniformDist<f64>, f64>(&mut rng, &params, n_tries);
+
+        let params = vec![4.0, 5.0];
+        test_dist::<TruncatedPowerLawDist<f64>, f64>(&mut rng, &params, n_tries);
+
+        let params = vec![0.3, 0.7];
+        test_dist::<BinaryDist<f64>, f64>(&mut rng, &params, n_tries);
     }
 }
@@ -0,0 +1,125 @@
+//! Dirichlet-to-Neumann response matrix of the boundary of a sample.
+//!
+//! The two ideal buses are split into one terminal per bus bond, at the outer end of the bond.
+//! Only the bus sides carry terminals: the first and last rows are free edges, through which no
+//! current leaves, and on a periodic lattice there are none.
+//! The response matrix is the Schur complement of the Laplacian onto those terminals: entry
+//! (s, t) is the current entering the network through terminal s when terminal t is held at unit
+//! potential and every other terminal is grounded. With the Green's function G of the grounded
+//! Laplacian it reads Lambda_st = g_s delta_st - g_s g_t G(n_s, n_t), where terminal s is joined
+//! to node n_s by a bond of conductance g_s.
+use crate::config::Precision;
+use crate::green::GreenFunction;
+use crate::lattice::{bonds, Bond};
+use crate::solver::{V_HGH, V_LOW, ZERO};
+
+pub struct ResponseMatrix {
+    /// Node each terminal is attached to: first the high bus bonds, then the low ones
+    pub nodes: Vec<usize>,
+    /// Number of terminals of the high bus
+    pub n_high: usize,
+    pub lambda: Vec<Vec<Precision>>,
+}
+
+impl ResponseMatrix {
+    /// Computes the response matrix of the sample `resist`.
+    pub unsafe fn new(resist: &[Precision]) -> Result<ResponseMatrix, String> {
+        let terminals = |high: bool| {
+            bonds()
+                .zip(resist)
+                .filter_map(move |(bond, &r)| match bond {
+                    Bond::High(idx) if high => Some((idx, r)),
+                    Bond::Low(idx) if !high => Some((idx, r)),
+                    _ => None,
+                })
+        };
+        let n_high = terminals(true).count();
+        let (nodes, g): (Vec<usize>, Vec<Precision>) =
+            terminals(true).chain(terminals(false)).unzip();
+
+        let green = GreenFunction::new(resist)?;
+        let n = nodes.len();
+        let mut lambda = vec![vec![ZERO; n]; n];
+
+        for t in 0..n {
+            let column = green.column(nodes[t]);
+            for s in 0..n {
+                lambda[s][t] = -g[s] * g[t] * column[nodes[s]];
+            }
+            lambda[t][t] += g[t];
+        }
+
+        Ok(ResponseMatrix {
+            nodes,
+            n_high,
+            lambda,
+        })
+    }
+
+    /// Currents entering the network through each terminal for the given terminal potentials.
+    pub fn currents(&self, voltages: &[Precision]) -> Vec<Precision> {
+        self.lambda
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(voltages)
+                    .fold(ZERO, |acc, (&l, &v)| acc + l * v)
+            })
+            .collect()
+    }
+
+    /// Conductance between the buses, with every terminal of each bus at the bus potential.
+    pub fn bus_conductance(&self) -> Precision {
+        let voltages: Vec<Precision> = (0..self.nodes.len())
+            .map(|t| if t < self.n_high { V_HGH } else { V_LOW })
+            .collect();
+        let current = self.currents(&voltages)[..self.n_high]
+            .iter()
+            .fold(ZERO, |a, &b| a + b);
+
+        current / (V_HGH - V_LOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::Float;
+    use crate::solver::MATRIX_LOCK;
+    use crate::starmesh;
+    use approx::assert_relative_eq;
+    use rand::Rng;
+
+    const EPS: f64 = 1e-10;
+
+    #[test]
+    fn test_response_matrix() {
+        let mut rng = rand::rng();
+        let resist: Vec<Precision> = bonds()
+            .map(|_| Precision::new(0.1 + rng.random::<f64>()))
+            .collect();
+
+        let response = {
+            let _lock = MATRIX_LOCK.lock().unwrap();
+            unsafe { ResponseMatrix::new(&resist).unwrap() }
+        };
+        let n = response.nodes.len();
+        for s in 0..n {
+            let row_sum = response.lambda[s].iter().fold(ZERO, |a, &b| a + b);
+            assert_relative_eq!(row_sum.into_f64(), 0.0, epsilon = EPS);
+            for t in 0..s {
+                assert_relative_eq!(
+                    response.lambda[s][t].into_f64(),
+                    response.lambda[t][s].into_f64(),
+                    epsilon = EPS
+                );
+            }
+        }
+
+        assert_relative_eq!(
+            response.bus_conductance().into_f64(),
+            starmesh::effective_conductance(&resist).into_f64(),
+            max_relative = EPS
+        );
+    }
+}
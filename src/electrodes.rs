@@ -0,0 +1,277 @@
+//! Electrodes at arbitrary sets of nodes.
+//!
+//! An electrode specification replaces the two buses, whose bonds are left out, by electrodes
+//! holding sets of nodes at given voltages: point contacts, four-probe layouts or partial edges.
+//! Every line of the specification file is an electrode, given by its voltage followed by its
+//! nodes, each as `row:col` or as inclusive ranges `row0-row1:col0-col1`. Empty lines and lines
+//! starting with `#` are skipped. For example, two point contacts in the middle of the first and
+//! last columns of a 35 x 35 lattice:
+//!
+//! ```text
+//! # voltage  nodes
+//!  1.0       17:0
+//! -1.0       17:32
+//! ```
+use crate::config::{Precision, LX, LY, N_UNK};
+use crate::float::Float;
+use crate::lattice::{bonds, fidx, Bond};
+use crate::percolation;
+use crate::solver::{empty_matrix, MATRIX, ZERO};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+pub struct Electrode {
+    pub voltage: f64,
+    pub nodes: Vec<usize>,
+}
+
+pub struct Electrodes {
+    /// Name of the specification, used in file names
+    pub name: String,
+    pub electrodes: Vec<Electrode>,
+    /// Electrode holding each node, if any
+    owner: Vec<Option<usize>>,
+}
+
+/// Parses `n` or `n0-n1` as an inclusive range of indices below `len`.
+fn parse_range(text: &str, len: usize) -> Result<RangeInclusive<usize>, String> {
+    let (first, last) = text.split_once('-').unwrap_or((text, text));
+    let parse = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|e| format!("Invalid index {}: {}", s, e))
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+
+    if first > last || last >= len {
+        return Err(format!("Range {} is not within 0-{}", text, len - 1));
+    }
+    Ok(first..=last)
+}
+
+/// Name and contents of the specification file at `path`, the name being its stem.
+pub fn read_spec<P: AsRef<Path>>(path: P) -> Result<(String, String), String> {
+    let path = path.as_ref();
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let name = path
+        .file_stem()
+        .map_or(String::from("spec"), |s| s.to_string_lossy().into_owned());
+    Ok((name, text))
+}
+
+/// Sets of nodes given by the lines of a specification, each with the value of the first field
+/// of its line parsed by `parse_value`. A node can only be in one set.
+pub fn parse_node_sets<V, F>(text: &str, parse_value: F) -> Result<Vec<(V, Vec<usize>)>, String>
+where
+    F: Fn(&str) -> Result<V, String>,
+{
+    let mut sets: Vec<(V, Vec<usize>)> = Vec::new();
+    let mut owner: Vec<Option<usize>> = vec![None; N_UNK];
+
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    for (n, line) in lines {
+        let err = |e: String| format!("Line {}: {}", n + 1, e);
+        let mut fields = line.split_whitespace();
+        let value = parse_value(fields.next().unwrap()).map_err(err)?;
+
+        let mut nodes: Vec<usize> = Vec::new();
+        for field in fields {
+            let (rows, cols) = field
+                .split_once(':')
+                .ok_or_else(|| err(format!("Node {} is not of the form row:col", field)))?;
+            let rows = parse_range(rows, LX).map_err(err)?;
+            let cols = parse_range(cols, LY - 2).map_err(err)?;
+
+            for col in cols {
+                for row in rows.clone() {
+                    let idx = fidx(row, col);
+                    if let Some(other) = owner[idx] {
+                        return Err(err(format!(
+                            "Node {}:{} is already in set {}",
+                            row, col, other
+                        )));
+                    }
+                    owner[idx] = Some(sets.len());
+                    nodes.push(idx);
+                }
+            }
+        }
+
+        if nodes.is_empty() {
+            return Err(err(String::from("Line without nodes")));
+        }
+        sets.push((value, nodes));
+    }
+
+    Ok(sets)
+}
+
+impl Electrodes {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Electrodes, String> {
+        let (name, text) = read_spec(path)?;
+        Electrodes::parse(&name, &text)
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Electrodes, String> {
+        let sets = parse_node_sets(text, |v| {
+            v.parse::<f64>()
+                .map_err(|e| format!("Invalid voltage {}: {}", v, e))
+        })?;
+        if sets.is_empty() {
+            return Err(String::from("No electrodes given"));
+        }
+
+        let mut owner: Vec<Option<usize>> = vec![None; N_UNK];
+        let electrodes = sets
+            .into_iter()
+            .enumerate()
+            .map(|(e, (voltage, nodes))| {
+                for &idx in nodes.iter() {
+                    owner[idx] = Some(e);
+                }
+                Electrode { voltage, nodes }
+            })
+            .collect();
+
+        Ok(Electrodes {
+            name: name.to_string(),
+            electrodes,
+            owner,
+        })
+    }
+
+    /// Conductances of the bonds between nodes, those to the buses removed.
+    fn internal(resist: &[Precision]) -> Vec<Precision> {
+        bonds()
+            .zip(resist)
+            .map(|(bond, &r)| match bond {
+                Bond::Internal(_, _) => r,
+                _ => ZERO,
+            })
+            .collect()
+    }
+
+    /// Assembles the system of `resist` driven by the electrodes, with identity rows for the
+    /// nodes held by them, and returns the nodes left in the system.
+    #[allow(static_mut_refs)]
+    pub unsafe fn build_system(&self, resist: &[Precision]) -> Vec<bool> {
+        let resist = Electrodes::internal(resist);
+        empty_matrix();
+
+        for (bond, &r) in bonds().zip(resist.iter()) {
+            if let Bond::Internal(a, b) = bond {
+                for (p, q) in [(a, b), (b, a)] {
+                    MATRIX[p][p] += -r;
+                    MATRIX[p][q] += r;
+                }
+            }
+        }
+
+        for electrode in self.electrodes.iter() {
+            for &idx in electrode.nodes.iter() {
+                MATRIX[idx].fill(ZERO);
+                MATRIX[idx][idx] = Precision::ONE;
+                MATRIX[idx][N_UNK] = Precision::new(electrode.voltage);
+            }
+        }
+
+        // Clusters joined to no electrode are left out of the system, at zero potential
+        let active =
+            percolation::connected(N_UNK, bonds(), &resist, |idx| self.owner[idx].is_some());
+        for idx in (0..N_UNK).filter(|&idx| !active[idx]) {
+            MATRIX[idx].fill(ZERO);
+            MATRIX[idx][idx] = Precision::ONE;
+        }
+
+        active
+    }
+
+    /// Current flowing out of each electrode into the rest of the network, for potentials `x`.
+    pub fn currents(&self, resist: &[Precision], x: &[Precision]) -> Vec<Precision> {
+        let mut current = vec![ZERO; self.electrodes.len()];
+
+        for (bond, &r) in bonds().zip(resist) {
+            if let Bond::Internal(a, b) = bond {
+                if self.owner[a] == self.owner[b] {
+                    continue;
+                }
+                if let Some(e) = self.owner[a] {
+                    current[e] += r * (x[a] - x[b]);
+                }
+                if let Some(e) = self.owner[b] {
+                    current[e] += r * (x[b] - x[a]);
+                }
+            }
+        }
+
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LATTICE, N_RES};
+    use crate::lattice::{coords, displacement};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_parse() {
+        let spec = format!(
+            "# Partial edge and point contact\n\n1.5 0-2:0 5:1\n-1 0:{}\n",
+            LY - 3
+        );
+        let electrodes = Electrodes::parse("test", &spec).unwrap();
+
+        assert_eq!(electrodes.electrodes.len(), 2);
+        assert_eq!(electrodes.electrodes[0].voltage, 1.5);
+        assert_eq!(
+            electrodes.electrodes[0].nodes,
+            [fidx(0, 0), fidx(1, 0), fidx(2, 0), fidx(5, 1)]
+        );
+        assert_eq!(electrodes.electrodes[1].nodes, [fidx(0, LY - 3)]);
+
+        for spec in [
+            String::new(),
+            String::from("1.0"),
+            String::from("1.0 0:0\n-1.0 0:0"),
+            format!("1.0 {}:0", LX),
+            String::from("1.0 2-1:0"),
+            String::from("1.0 0,0"),
+            String::from("high 0:0"),
+        ] {
+            assert!(Electrodes::parse("test", &spec).is_err(), "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn test_currents() {
+        // Electrodes on the first and last columns of a uniform network without diagonals, with
+        // the potential dropping linearly between them
+        let spec = format!("1 0-{}:0\n-1 0-{}:{}", LX - 1, LX - 1, LY - 3);
+        let electrodes = Electrodes::parse("test", &spec).unwrap();
+
+        let resist: Vec<f64> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b).0 * displacement(a, b).1 != 0 => 0.0,
+                _ => 1.0,
+            })
+            .collect();
+        assert_eq!(resist.len(), N_RES);
+        let step = 2.0 / (LY - 3) as f64;
+        let x: Vec<Precision> = (0..N_UNK)
+            .map(|idx| Precision::new(1.0 - step * coords(idx).1 as f64))
+            .collect();
+        let resist: Vec<Precision> = resist.into_iter().map(Precision::new).collect();
+
+        let current = electrodes.currents(&resist, &x);
+        let expected = LATTICE.link_bonds(LX).filter(|&(a, b)| a == b).count() as f64 * step;
+        assert_relative_eq!(current[0].into_f64(), expected, epsilon = 1e-9);
+        assert_relative_eq!(current[1].into_f64(), -expected, epsilon = 1e-9);
+    }
+}
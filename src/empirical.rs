@@ -0,0 +1,511 @@
+//! Empirical distributions of measured conductances.
+//!
+//! A file holds either measured samples, a value on every line, or a histogram, a bin
+//! `low high count` on every line. Empty lines and lines starting with `#` are skipped. Values
+//! are drawn through the inverse of the empirical distribution function: one of the samples at
+//! random, or a value spread uniformly over a bin picked with the weight of its count.
+//!
+//! Samples can be smoothed by a lognormal kernel of width `h` in `ln g` around each of them,
+//! which keeps the conductances positive. The width is given, or chosen by Silverman's rule of
+//! thumb on the logarithms of the samples.
+use crate::dists::{uniform_log_moments, Distribution};
+use crate::electrodes::read_spec;
+use crate::float::Float;
+use crate::math;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Points of the table of the distribution function of smoothed samples.
+const TABLE_POINTS: usize = 1024;
+
+/// Widths of the kernel beyond the extreme samples covered by the table.
+const TABLE_MARGIN: f64 = 6.0;
+
+/// Measured data of an empirical distribution.
+pub enum Data {
+    /// Measured values, in increasing order
+    Samples(Vec<f64>),
+    /// Bins `(low, high)` with the fraction of the counts up to the end of each
+    Histogram {
+        bins: Vec<(f64, f64)>,
+        cumulative: Vec<f64>,
+    },
+}
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    text.parse::<f64>()
+        .map_err(|e| format!("Invalid number {}: {}", text, e))
+}
+
+impl Data {
+    /// Samples or histogram from the lines of `text`, told apart by their number of fields.
+    pub fn parse(text: &str) -> Result<Data, String> {
+        let mut samples: Vec<f64> = Vec::new();
+        let mut bins: Vec<(f64, f64)> = Vec::new();
+        let mut counts: Vec<f64> = Vec::new();
+
+        let lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+        for (n, line) in lines {
+            let err = |e: String| format!("Line {}: {}", n + 1, e);
+            let fields: Vec<f64> = line
+                .split_whitespace()
+                .map(parse_number)
+                .collect::<Result<_, _>>()
+                .map_err(err)?;
+
+            match fields[..] {
+                [g] if bins.is_empty() => {
+                    if g < 0.0 || !g.is_finite() {
+                        return Err(err(format!("Invalid conductance {}", g)));
+                    }
+                    samples.push(g);
+                }
+                [low, high, count] if samples.is_empty() => {
+                    if low < 0.0 || high <= low || !high.is_finite() {
+                        return Err(err(format!("Invalid bin from {} to {}", low, high)));
+                    }
+                    if count < 0.0 || !count.is_finite() {
+                        return Err(err(format!("Invalid count {}", count)));
+                    }
+                    if bins.last().is_some_and(|&(_, end)| low < end) {
+                        return Err(err(String::from("Bins must increase without overlapping")));
+                    }
+                    bins.push((low, high));
+                    counts.push(count);
+                }
+                [_] | [_, _, _] => {
+                    return Err(err(String::from("Samples and bins cannot be mixed")));
+                }
+                _ => {
+                    return Err(err(String::from(
+                        "Expected a sample or a bin with its count",
+                    )))
+                }
+            }
+        }
+
+        if !bins.is_empty() {
+            let total: f64 = counts.iter().sum();
+            if total <= 0.0 {
+                return Err(String::from("A histogram needs a positive count"));
+            }
+            let cumulative = counts
+                .iter()
+                .scan(0.0, |acc, &count| {
+                    *acc += count / total;
+                    Some(*acc)
+                })
+                .collect();
+            return Ok(Data::Histogram { bins, cumulative });
+        }
+
+        if samples.is_empty() {
+            return Err(String::from("No samples or bins"));
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Ok(Data::Samples(samples))
+    }
+}
+
+/// Width of the kernel by Silverman's rule of thumb on the logarithms of `samples`.
+fn silverman(samples: &[f64]) -> f64 {
+    let (_, var) = log_moments(samples);
+    1.06 * var.sqrt() * (samples.len() as f64).powf(-0.2)
+}
+
+/// Bins of a histogram with the fraction of the data in each of them.
+fn weighted<'a>(
+    bins: &'a [(f64, f64)],
+    cumulative: &'a [f64],
+) -> impl Iterator<Item = (f64, f64, f64)> + 'a {
+    bins.iter()
+        .zip(cumulative.iter())
+        .scan(0.0, |below, (&(low, high), &c)| {
+            let weight = c - *below;
+            *below = c;
+            Some((weight, low, high))
+        })
+}
+
+/// Mean and variance of the logarithms of `samples`.
+fn log_moments(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().map(|g| g.ln()).sum::<f64>() / n;
+    let var = samples.iter().map(|g| (g.ln() - mean).powi(2)).sum::<f64>() / n;
+    (mean, var)
+}
+
+/// Distribution of measured conductances.
+pub struct Empirical<T: Float> {
+    /// Name of the file of the data, used in file names
+    name: String,
+    data: Data,
+    /// Width of the smoothing kernel in `ln g`
+    bandwidth: Option<f64>,
+    /// Points `(ln g, F)` of the distribution function of the smoothed samples
+    table: Vec<(f64, f64)>,
+    precision: PhantomData<T>,
+}
+
+impl<T: Float> Empirical<T> {
+    /// Loads the data at `path`, smoothed by a kernel of width `bandwidth`: a number, or `auto`
+    /// for Silverman's rule.
+    pub fn load<P: AsRef<Path>>(path: P, bandwidth: Option<&str>) -> Result<Empirical<T>, String> {
+        let (name, text) = read_spec(path)?;
+        let data = Data::parse(&text)?;
+
+        let bandwidth = match (bandwidth, &data) {
+            (None, _) => None,
+            (Some(_), Data::Histogram { .. }) => {
+                return Err(String::from("Only samples can be smoothed, not histograms"))
+            }
+            (Some("auto"), Data::Samples(samples)) if samples.len() < 2 => {
+                return Err(String::from("Silverman's rule needs at least two samples"))
+            }
+            (Some("auto"), Data::Samples(samples)) => Some(silverman(samples)),
+            (Some(h), _) => Some(parse_number(h)?),
+        };
+        Empirical::with(name, data, bandwidth)
+    }
+
+    pub fn with(name: String, data: Data, bandwidth: Option<f64>) -> Result<Empirical<T>, String> {
+        let mut table: Vec<(f64, f64)> = Vec::new();
+
+        if let (Some(h), Data::Samples(samples)) = (bandwidth, &data) {
+            if h <= 0.0 || !h.is_finite() {
+                return Err(format!(
+                    "The width of the kernel must be positive, got {}",
+                    h
+                ));
+            }
+            if samples[0] <= 0.0 {
+                return Err(String::from("Only positive samples can be smoothed"));
+            }
+            let logs: Vec<f64> = samples.iter().map(|g| g.ln()).collect();
+            let start = logs[0] - TABLE_MARGIN * h;
+            let step =
+                (logs[logs.len() - 1] + TABLE_MARGIN * h - start) / (TABLE_POINTS - 1) as f64;
+            table = (0..TABLE_POINTS)
+                .map(|k| {
+                    let y = start + k as f64 * step;
+                    let f = logs
+                        .iter()
+                        .map(|x| math::normal_cdf((y - x) / h))
+                        .sum::<f64>();
+                    (y, f / logs.len() as f64)
+                })
+                .collect();
+        }
+
+        Ok(Empirical {
+            name,
+            data,
+            bandwidth,
+            table,
+            precision: PhantomData,
+        })
+    }
+
+    /// Value below which a fraction `u` of the data fall.
+    fn inverse(&self, u: f64) -> f64 {
+        match &self.data {
+            Data::Samples(_) if !self.table.is_empty() => {
+                let k = self.table.partition_point(|&(_, f)| f <= u);
+                if k == 0 {
+                    return self.table[0].0.exp();
+                }
+                if k == self.table.len() {
+                    return self.table[k - 1].0.exp();
+                }
+                let ((y0, f0), (y1, f1)) = (self.table[k - 1], self.table[k]);
+                (y0 + (y1 - y0) * (u - f0) / (f1 - f0)).exp()
+            }
+            Data::Samples(samples) => {
+                let k = (u * samples.len() as f64) as usize;
+                samples[k.min(samples.len() - 1)]
+            }
+            Data::Histogram { bins, cumulative } => {
+                let b = cumulative.partition_point(|&c| c <= u).min(bins.len() - 1);
+                let below = if b == 0 { 0.0 } else { cumulative[b - 1] };
+                let weight = cumulative[b] - below;
+                let (low, high) = bins[b];
+                if weight == 0.0 {
+                    return high;
+                }
+                low + (high - low) * ((u - below) / weight).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl<T: Float> fmt::Display for Empirical<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bandwidth {
+            Some(h) => write!(f, "emp({},h={:.3})", self.name, h),
+            None => write!(f, "emp({})", self.name),
+        }
+    }
+}
+
+impl<T: Float> Distribution<T> for Empirical<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        match (&self.data, self.bandwidth) {
+            (Data::Samples(samples), Some(h)) => {
+                let g = samples[rng.random_range(0..samples.len())];
+                let z = math::normal_quantile(rng.random_range(f64::EPSILON..1.0));
+                T::new(g * (h * z).exp())
+            }
+            _ => T::new(self.inverse(rng.random::<f64>())),
+        }
+    }
+
+    /// Measured samples are discrete values, unless they are smoothed.
+    fn pdf(&self, x: T) -> T {
+        let x = x.into_f64();
+        let density = match (&self.data, self.bandwidth) {
+            (Data::Samples(samples), Some(h)) if x > 0.0 => {
+                let kernel = |g: f64| {
+                    let z = (x / g).ln() / h;
+                    (-z * z / 2.0).exp() / ((2.0 * std::f64::consts::PI).sqrt() * x * h)
+                };
+                samples.iter().map(|&g| kernel(g)).sum::<f64>() / samples.len() as f64
+            }
+            (Data::Samples(_), _) => 0.0,
+            (Data::Histogram { bins, cumulative }, _) => weighted(bins, cumulative)
+                .filter(|&(_, low, high)| low <= x && x < high)
+                .map(|(weight, low, high)| weight / (high - low))
+                .sum(),
+        };
+        T::new(density)
+    }
+
+    fn cdf(&self, x: T) -> T {
+        let x = x.into_f64();
+        let fraction = match (&self.data, self.bandwidth) {
+            (Data::Samples(_), Some(_)) if x <= 0.0 => 0.0,
+            (Data::Samples(samples), Some(h)) => {
+                let kernel = |g: f64| math::normal_cdf((x / g).ln() / h);
+                samples.iter().map(|&g| kernel(g)).sum::<f64>() / samples.len() as f64
+            }
+            (Data::Samples(samples), None) => {
+                samples.partition_point(|&g| g <= x) as f64 / samples.len() as f64
+            }
+            (Data::Histogram { bins, cumulative }, _) => weighted(bins, cumulative)
+                .map(|(weight, low, high)| match x >= high {
+                    true => weight,
+                    false => weight * ((x - low) / (high - low)).max(0.0),
+                })
+                .sum(),
+        };
+        T::new(fraction)
+    }
+
+    fn quantile(&self, u: T) -> T {
+        T::new(self.inverse(u.into_f64()))
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        let mean = match &self.data {
+            Data::Samples(samples) => {
+                let kernel = self.bandwidth.map_or(1.0, |h| (h * h / 2.0).exp());
+                kernel * samples.iter().sum::<f64>() / samples.len() as f64
+            }
+            Data::Histogram { bins, cumulative } => weighted(bins, cumulative)
+                .map(|(weight, low, high)| weight * (low + high) / 2.0)
+                .sum(),
+        };
+        T::new(mean)
+    }
+
+    fn variance(&self) -> T {
+        let mean = self.mean().into_f64();
+        let square = match &self.data {
+            Data::Samples(samples) => {
+                let kernel = self.bandwidth.map_or(1.0, |h| (2.0 * h * h).exp());
+                kernel * samples.iter().map(|g| g * g).sum::<f64>() / samples.len() as f64
+            }
+            Data::Histogram { bins, cumulative } => weighted(bins, cumulative)
+                .map(|(weight, low, high)| weight * (low * low + low * high + high * high) / 3.0)
+                .sum(),
+        };
+        T::new(square - mean * mean)
+    }
+
+    /// Diverges to minus infinity if some of the data are zero.
+    fn mean_log(&self) -> T {
+        let mean = match &self.data {
+            Data::Samples(samples) => log_moments(samples).0,
+            Data::Histogram { bins, cumulative } => weighted(bins, cumulative)
+                .map(|(weight, low, high)| weight * uniform_log_moments(low, high).0)
+                .sum(),
+        };
+        T::new(mean)
+    }
+
+    fn var_log(&self) -> T {
+        let var = match &self.data {
+            Data::Samples(samples) => {
+                log_moments(samples).1 + self.bandwidth.map_or(0.0, |h| h * h)
+            }
+            Data::Histogram { bins, cumulative } => {
+                let mean = self.mean_log().into_f64();
+                let square = weighted(bins, cumulative)
+                    .map(|(weight, low, high)| {
+                        let (m, v) = uniform_log_moments(low, high);
+                        weight * (v + m * m)
+                    })
+                    .sum::<f64>();
+                square - mean * mean
+            }
+        };
+        T::new(var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dists::LogNormalDist;
+    use approx::assert_relative_eq;
+
+    const EPS: f64 = 5e-2;
+
+    fn empirical(text: &str, bandwidth: Option<f64>) -> Empirical<f64> {
+        Empirical::with(String::from("test"), Data::parse(text).unwrap(), bandwidth).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let Data::Samples(samples) = Data::parse("# g\n3\n\n1\n2.5\n").unwrap() else {
+            panic!("Expected samples");
+        };
+        assert_eq!(samples, vec![1.0, 2.5, 3.0]);
+
+        let Data::Histogram { bins, cumulative } = Data::parse("0 1 1\n1 3 3\n").unwrap() else {
+            panic!("Expected a histogram");
+        };
+        assert_eq!(bins, vec![(0.0, 1.0), (1.0, 3.0)]);
+        assert_eq!(cumulative, vec![0.25, 1.0]);
+
+        for text in [
+            "",
+            "# nothing\n",
+            "1\n-2\n",
+            "1\nx\n",
+            "1 2\n",
+            "1\n0 1 2\n",
+            "0 1 2\n3\n",
+            "1 0 2\n",
+            "0 1 -2\n",
+            "0 2 1\n1 3 1\n",
+            "0 1 0\n",
+        ] {
+            assert!(Data::parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_samples() {
+        let mut rng = rand::rng();
+        let dist = empirical("1\n2\n4\n8\n", None);
+        assert_eq!(dist.to_string(), "emp(test)");
+        assert_relative_eq!(dist.mean(), 3.75, epsilon = 1e-12);
+
+        for (u, g) in [(0.0, 1.0), (0.3, 2.0), (0.6, 4.0), (0.99, 8.0), (1.0, 8.0)] {
+            assert_eq!(dist.quantile(u), g);
+        }
+        let data = [1.0, 2.0, 4.0, 8.0];
+        assert!((0..1000).all(|_| data.contains(&dist.sample(&mut rng))));
+
+        for (g, u) in [(0.5, 0.0), (2.0, 0.5), (3.0, 0.5), (8.0, 1.0)] {
+            assert_eq!(dist.cdf(g), u);
+            assert_eq!(dist.pdf(g), 0.0);
+        }
+        let ln2 = 2f64.ln();
+        assert_relative_eq!(dist.variance(), 85.0 / 4.0 - 3.75 * 3.75, epsilon = 1e-12);
+        assert_relative_eq!(dist.mean_log(), 1.5 * ln2, epsilon = 1e-12);
+        assert_relative_eq!(dist.var_log(), 1.25 * ln2 * ln2, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut rng = rand::rng();
+        let n_tries: usize = 10000;
+        let dist = empirical("0 1 1\n1 3 3\n", None);
+        assert_relative_eq!(dist.mean(), 0.25 * 0.5 + 0.75 * 2.0, epsilon = 1e-12);
+
+        for (u, g) in [
+            (0.0, 0.0),
+            (0.125, 0.5),
+            (0.25, 1.0),
+            (0.625, 2.0),
+            (1.0, 3.0),
+        ] {
+            assert_relative_eq!(dist.quantile(u), g, epsilon = 1e-12);
+        }
+        let actual = (0..n_tries).map(|_| dist.sample(&mut rng)).sum::<f64>() / n_tries as f64;
+        assert_relative_eq!(actual / dist.mean(), 1.0, epsilon = EPS);
+
+        for (g, u, density) in [
+            (-1.0, 0.0, 0.0),
+            (0.5, 0.125, 0.25),
+            (2.0, 0.625, 0.375),
+            (3.0, 1.0, 0.0),
+        ] {
+            assert_relative_eq!(dist.cdf(g), u, epsilon = 1e-12);
+            assert_relative_eq!(dist.pdf(g), density, epsilon = 1e-12);
+        }
+        let square = 0.25 / 3.0 + 0.75 * 13.0 / 3.0;
+        assert_relative_eq!(dist.variance(), square - 1.625 * 1.625, epsilon = 1e-12);
+        let mean_log = -0.25 + 0.75 * (3.0 * 3f64.ln() - 2.0) / 2.0;
+        assert_relative_eq!(dist.mean_log(), mean_log, epsilon = 1e-12);
+
+        // The variance of the logarithms against the integral of the quantile function
+        let n: usize = 100000;
+        let square = (0..n)
+            .map(|i| dist.quantile((i as f64 + 0.5) / n as f64).ln().powi(2))
+            .sum::<f64>()
+            / n as f64;
+        assert_relative_eq!(dist.var_log(), square - mean_log * mean_log, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_smoothing() {
+        // A single sample smoothed into a lognormal distribution of scale h around it
+        let mut rng = rand::rng();
+        let n_tries: usize = 10000;
+        let h = 0.5;
+        let dist = empirical("2\n", Some(h));
+        assert_eq!(dist.to_string(), "emp(test,h=0.500)");
+        assert_relative_eq!(dist.mean(), 2.0 * (h * h / 2.0).exp(), epsilon = 1e-12);
+
+        for z in [-2.0, -0.5, 0.0, 1.0, 2.5] {
+            let g = dist.quantile(math::normal_cdf(z));
+            assert_relative_eq!(g.ln(), 2f64.ln() + h * z, epsilon = 1e-3);
+        }
+        let actual = (0..n_tries).map(|_| dist.sample(&mut rng)).sum::<f64>() / n_tries as f64;
+        assert_relative_eq!(actual / dist.mean(), 1.0, epsilon = EPS);
+
+        let lognormal: LogNormalDist<f64> = LogNormalDist::with(2f64.ln(), h);
+        for g in [0.5, 1.0, 2.0, 4.0] {
+            assert_relative_eq!(dist.cdf(g), lognormal.cdf(g), epsilon = 1e-12);
+            assert_relative_eq!(dist.pdf(g), lognormal.pdf(g), epsilon = 1e-12);
+        }
+        assert_eq!(dist.cdf(0.0), 0.0);
+        assert_relative_eq!(dist.variance(), lognormal.variance(), epsilon = 1e-12);
+        assert_relative_eq!(dist.mean_log(), lognormal.mean_log(), epsilon = 1e-12);
+        assert_relative_eq!(dist.var_log(), lognormal.var_log(), epsilon = 1e-12);
+
+        let data = Data::parse("0\n1\n").unwrap();
+        assert!(Empirical::<f64>::with(String::from("test"), data, Some(h)).is_err());
+        let data = Data::parse("1\n2\n").unwrap();
+        assert!(Empirical::<f64>::with(String::from("test"), data, Some(0.0)).is_err());
+    }
+}
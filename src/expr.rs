@@ -0,0 +1,653 @@
+//! Distributions given by an expression `g = f(u)` of a uniform variate `u` in `[0, 1)`.
+//!
+//! Expressions are made of numbers, the variate `u`, the constant `pi`, named parameters, the
+//! operators `+ - * / ^` with the usual precedence, parentheses and the functions `exp`, `ln`,
+//! `sqrt`, `erf` and `pow(x, y)`. They are evaluated in the precision of the run, so that for
+//! instance `exp(a*u)` draws the same values as the inverse distribution of parameter `a`.
+//!
+//! The expression must give finite conductances of at least zero, monotone in `u`, so that it is
+//! also the quantile function of the distribution, or its mirror image if it decreases.
+use crate::dists::{Distribution, Param};
+use crate::float::Float;
+use rand::rngs::ThreadRng;
+use std::fmt;
+
+/// Points in `(0, 1)` at which an expression is checked and integrated.
+const GRID_POINTS: usize = 100000;
+
+/// Most halvings of the interval of `u` when inverting an expression.
+const MAX_BISECTIONS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Exp,
+    Ln,
+    Sqrt,
+    Erf,
+    Pow,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "exp" => Some(Func::Exp),
+            "ln" => Some(Func::Ln),
+            "sqrt" => Some(Func::Sqrt),
+            "erf" => Some(Func::Erf),
+            "pow" => Some(Func::Pow),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Pow => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Number(f64),
+    Uniform,
+    Pi,
+    /// Index of a parameter in the order of their first appearance
+    Param(usize),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || chars[i] == '.'
+                    || (matches!(chars[i], 'e' | 'E') && i + 1 < chars.len())
+                    || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid number {}: {}", number, e))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("Unexpected character {}", c));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of an expression.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Names of the parameters, in the order of their first appearance
+    params: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected {}", symbol))
+        }
+    }
+
+    /// `sum := product (('+' | '-') product)*`
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    /// `product := unary (('*' | '/') unary)*`
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    /// `unary := '-' unary | power`
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    /// `power := atom ('^' unary)?`, so that powers group to the right
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(Expr::Binary(
+                Op::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// `atom := number | name | name '(' sum (',' sum)* ')' | '(' sum ')'`
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| String::from("Unexpected end of the expression"))?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Symbol('(') => {
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::Name(name) if self.eat('(') => {
+                let func =
+                    Func::from_name(&name).ok_or_else(|| format!("Unknown function {}", name))?;
+                let mut args = vec![self.sum()?];
+                while self.eat(',') {
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+                if args.len() != func.arity() {
+                    return Err(format!(
+                        "Function {} takes {} arguments, got {}",
+                        name,
+                        func.arity(),
+                        args.len()
+                    ));
+                }
+                Ok(Expr::Call(func, args))
+            }
+            Token::Name(name) => match name.as_str() {
+                "u" => Ok(Expr::Uniform),
+                "pi" => Ok(Expr::Pi),
+                _ if Func::from_name(&name).is_some() => {
+                    Err(format!("Function {} needs its arguments", name))
+                }
+                _ => {
+                    let idx = match self.params.iter().position(|p| *p == name) {
+                        Some(idx) => idx,
+                        None => {
+                            self.params.push(name);
+                            self.params.len() - 1
+                        }
+                    };
+                    Ok(Expr::Param(idx))
+                }
+            },
+            Token::Symbol(c) => Err(format!("Unexpected {}", c)),
+        }
+    }
+}
+
+impl Expr {
+    /// Parses `text`, returning the expression and the names of its parameters.
+    fn parse(text: &str) -> Result<(Expr, Vec<String>), String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+            params: Vec::new(),
+        };
+        let expr = parser.sum()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {:?} after the expression", token));
+        }
+        Ok((expr, parser.params))
+    }
+
+    fn eval<T: Float>(&self, u: T, params: &[T]) -> T {
+        match self {
+            Expr::Number(value) => T::new(*value),
+            Expr::Uniform => u,
+            Expr::Pi => T::PI,
+            Expr::Param(idx) => params[*idx],
+            Expr::Neg(a) => -a.eval(u, params),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(u, params), b.eval(u, params));
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => a.powf(b),
+                }
+            }
+            Expr::Call(func, args) => {
+                let x = args[0].eval(u, params);
+                match func {
+                    Func::Exp => x.exp(),
+                    Func::Ln => x.ln(),
+                    Func::Sqrt => x.sqrt(),
+                    Func::Erf => x.erf(),
+                    Func::Pow => x.powf(args[1].eval(u, params)),
+                }
+            }
+        }
+    }
+
+    /// Value and derivative with respect to `u`, carried together through the expression.
+    fn eval_slope<T: Float>(&self, u: T, params: &[T]) -> (T, T) {
+        match self {
+            Expr::Number(_) | Expr::Pi | Expr::Param(_) => (self.eval(u, params), T::ZERO),
+            Expr::Uniform => (u, T::ONE),
+            Expr::Neg(a) => {
+                let (a, da) = a.eval_slope(u, params);
+                (-a, -da)
+            }
+            Expr::Binary(op, a, b) => {
+                let ((a, da), (b, db)) = (a.eval_slope(u, params), b.eval_slope(u, params));
+                match op {
+                    Op::Add => (a + b, da + db),
+                    Op::Sub => (a - b, da - db),
+                    Op::Mul => (a * b, da * b + a * db),
+                    Op::Div => (a / b, (da * b - a * db) / (b * b)),
+                    Op::Pow => power((a, da), (b, db)),
+                }
+            }
+            Expr::Call(func, args) => {
+                let (x, dx) = args[0].eval_slope(u, params);
+                match func {
+                    Func::Exp => (x.exp(), x.exp() * dx),
+                    Func::Ln => (x.ln(), dx / x),
+                    Func::Sqrt => (x.sqrt(), dx / (T::TWO * x.sqrt())),
+                    Func::Erf => (x.erf(), T::TWO / T::PI.sqrt() * (-x * x).exp() * dx),
+                    Func::Pow => power((x, dx), args[1].eval_slope(u, params)),
+                }
+            }
+        }
+    }
+}
+
+/// Value and derivative of `a^b` from those of `a` and `b`.
+fn power<T: Float>((a, da): (T, T), (b, db): (T, T)) -> (T, T) {
+    let value = a.powf(b);
+    if db == T::ZERO {
+        (value, b * a.powf(b - T::ONE) * da)
+    } else {
+        (value, value * (db * a.ln() + b * da / a))
+    }
+}
+
+/// Mean and variance of `values`.
+fn moments(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, var)
+}
+
+/// Prints the expression in function form, `exp(mul(a,u))`, which is fit for file names.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(value) => write!(f, "{}", value),
+            Expr::Uniform => write!(f, "u"),
+            Expr::Pi => write!(f, "pi"),
+            Expr::Param(idx) => write!(f, "#{}", idx),
+            Expr::Neg(a) => write!(f, "neg({})", a),
+            Expr::Binary(op, a, b) => {
+                let name = match op {
+                    Op::Add => "add",
+                    Op::Sub => "sub",
+                    Op::Mul => "mul",
+                    Op::Div => "div",
+                    Op::Pow => "pow",
+                };
+                write!(f, "{}({},{})", name, a, b)
+            }
+            Expr::Call(func, args) => {
+                let name = format!("{:?}", func).to_lowercase();
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", name, args.join(","))
+            }
+        }
+    }
+}
+
+/// Distribution of `f(u)` for an expression `f` of a uniform variate `u`.
+pub struct ExprDist<T: Float> {
+    expr: Expr,
+    names: Vec<String>,
+    params: Vec<T>,
+    /// Whether the expression decreases with `u`, so that its quantile is `f(1 - u)`
+    decreasing: bool,
+}
+
+impl<T: Float> ExprDist<T> {
+    /// Distribution of the expression `text` with the parameters `name=value` of `values`,
+    /// separated by commas.
+    pub fn parse(text: &str, values: &str) -> Result<ExprDist<T>, String> {
+        let (expr, names) = Expr::parse(text)?;
+
+        let mut params: Vec<Option<f64>> = vec![None; names.len()];
+        for pair in values.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, value) = pair
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .ok_or_else(|| format!("Parameter {} is not of the form name=value", pair))?;
+            let idx = names
+                .iter()
+                .position(|n| n == name)
+                .ok_or_else(|| format!("Parameter {} does not appear in the expression", name))?;
+            if params[idx].is_some() {
+                return Err(format!("Parameter {} given twice", name));
+            }
+            let value = value
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid value {} of parameter {}: {}", value, name, e))?;
+            params[idx] = Some(value);
+        }
+        let params = names
+            .iter()
+            .zip(params)
+            .map(|(name, value)| {
+                value
+                    .map(T::new)
+                    .ok_or_else(|| format!("Parameter {} has no value", name))
+            })
+            .collect::<Result<Vec<T>, String>>()?;
+
+        let mut dist = ExprDist {
+            expr,
+            names,
+            params,
+            decreasing: false,
+        };
+        let values = dist.grid();
+        if let Some(g) = values.iter().find(|g| !g.is_finite() || **g < 0.0) {
+            return Err(format!(
+                "The expression must give finite conductances of at least zero, got {}",
+                g
+            ));
+        }
+        let rising = values.windows(2).all(|w| w[1] >= w[0]);
+        let falling = values.windows(2).all(|w| w[1] <= w[0]);
+        if !rising && !falling {
+            return Err(String::from("The expression must be monotone in u"));
+        }
+        dist.decreasing = !rising;
+        Ok(dist)
+    }
+
+    /// Values of the expression, in double precision, at the midpoints of a grid over `(0, 1)`.
+    fn grid(&self) -> Vec<f64> {
+        let params: Vec<f64> = self.params.iter().map(|p| p.into_f64()).collect();
+        (0..GRID_POINTS)
+            .map(|k| {
+                let u = (k as f64 + 0.5) / GRID_POINTS as f64;
+                self.expr.eval(u, &params)
+            })
+            .collect()
+    }
+}
+
+impl<T: Float> fmt::Display for ExprDist<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut label = self.expr.to_string();
+        for (idx, name) in self.names.iter().enumerate() {
+            label = label.replace(&format!("#{}", idx), name);
+        }
+        write!(f, "expr({}", label)?;
+        for (name, value) in self.names.iter().zip(self.params.iter()) {
+            write!(f, ",{}={}", name, Param(*value))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T: Float> Distribution<T> for ExprDist<T> {
+    fn sample(&self, rng: &mut ThreadRng) -> T {
+        self.expr.eval(T::random(rng), &self.params)
+    }
+
+    /// Inverse of the slope of the quantile function where it reaches `x`.
+    fn pdf(&self, x: T) -> T {
+        let u = self.cdf(x);
+        if u == T::ZERO || u == T::ONE {
+            return T::ZERO;
+        }
+        let u = if self.decreasing { T::ONE - u } else { u };
+        T::ONE / self.expr.eval_slope(u, &self.params).1.abs()
+    }
+
+    /// Inverts the quantile function by bisection, to the precision of `T`.
+    fn cdf(&self, x: T) -> T {
+        if x.lt(self.quantile(T::ZERO)) {
+            return T::ZERO;
+        }
+        if x.ge(self.quantile(T::ONE)) {
+            return T::ONE;
+        }
+        let (mut lo, mut hi) = (T::ZERO, T::ONE);
+        for _ in 0..MAX_BISECTIONS {
+            let mid = (lo + hi) / T::TWO;
+            if mid == lo || mid == hi {
+                break;
+            }
+            if x.ge(self.quantile(mid)) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo + hi) / T::TWO
+    }
+
+    fn quantile(&self, u: T) -> T {
+        let u = if self.decreasing { T::ONE - u } else { u };
+        self.expr.eval(u, &self.params)
+    }
+
+    /// Integral of the expression over `(0, 1)` by the midpoint rule, in double precision, as
+    /// are the other moments.
+    #[allow(unused)]
+    fn mean(&self) -> T {
+        T::new(self.grid().iter().sum::<f64>() / GRID_POINTS as f64)
+    }
+
+    fn variance(&self) -> T {
+        T::new(moments(&self.grid()).1)
+    }
+
+    fn mean_log(&self) -> T {
+        let logs: Vec<f64> = self.grid().iter().map(|g| g.ln()).collect();
+        T::new(moments(&logs).0)
+    }
+
+    fn var_log(&self) -> T {
+        let logs: Vec<f64> = self.grid().iter().map(|g| g.ln()).collect();
+        T::new(moments(&logs).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dists::{ExponentialDist, InverseDist, Parametric};
+    use approx::assert_relative_eq;
+    use f256::f256;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_parse() {
+        let eval = |text: &str| Expr::parse(text).unwrap().0.eval(0.5, &[2.0, 3.0]);
+        assert_relative_eq!(eval("1 + 2 * 3 - 4 / 2"), 5.0);
+        assert_relative_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_relative_eq!(eval("2 ^ 3 ^ 2"), 512.0, epsilon = 1e-9);
+        assert_relative_eq!(eval("-u ^ 2"), -0.25, epsilon = 1e-12);
+        assert_relative_eq!(eval("2 ^ -1"), 0.5, epsilon = 1e-12);
+        assert_relative_eq!(eval("a * u + b"), 4.0);
+        assert_relative_eq!(
+            eval("exp(ln(u)) + sqrt(4) + pow(u, 2)"),
+            2.75,
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(eval("1.5e-1 * 2E+1"), 3.0, epsilon = 1e-12);
+        assert_relative_eq!(eval("pi"), std::f64::consts::PI);
+
+        let (expr, names) = Expr::parse("exp(a * u) / b + a").unwrap();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(expr.to_string(), "add(div(exp(mul(#0,u)),#1),#0)");
+
+        for text in [
+            "",
+            "1 +",
+            "(1",
+            "1)",
+            "2 u",
+            "exp",
+            "exp(1, 2)",
+            "pow(2)",
+            "cos(u)",
+            "u $ 2",
+            "1e",
+        ] {
+            assert!(Expr::parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_expr_dist() {
+        let mut rng = rand::rng();
+
+        let dist: ExprDist<f64> = ExprDist::parse("exp(a*u)", "a=3").unwrap();
+        let inverse: InverseDist<f64> = InverseDist::new(3.0);
+        assert_eq!(dist.to_string(), "expr(exp(mul(a,u)),a=3.000)");
+        for u in [0.0, 0.2, 0.7, 0.99] {
+            assert_relative_eq!(dist.quantile(u), inverse.quantile(u), epsilon = 1e-12);
+        }
+        assert_relative_eq!(dist.mean() / inverse.mean(), 1.0, epsilon = 1e-6);
+        for g in [0.5, 2.0, 10.0, 30.0] {
+            assert_relative_eq!(dist.cdf(g), inverse.cdf(g), epsilon = 1e-12);
+            assert_relative_eq!(dist.pdf(g), inverse.pdf(g), epsilon = 1e-12);
+        }
+        assert_relative_eq!(dist.variance() / inverse.variance(), 1.0, epsilon = 1e-6);
+        assert_relative_eq!(dist.mean_log(), inverse.mean_log(), epsilon = 1e-6);
+        assert_relative_eq!(dist.var_log(), inverse.var_log(), epsilon = 1e-6);
+        let dist: ExprDist<f256> = ExprDist::parse("exp(a*u)", "a=30").unwrap();
+        let inverse: InverseDist<f256> = InverseDist::new(30.0);
+        for u in [0.2, 0.7] {
+            let u = f256::from(u);
+            assert_eq!(dist.quantile(u), inverse.quantile(u));
+            assert!((dist.cdf(dist.quantile(u)) - u).abs() < f256::from(1e-40));
+        }
+
+        let dist: ExprDist<f64> = ExprDist::parse("exp(a*u)", "a=3").unwrap();
+        assert!((0..1000)
+            .map(|_| dist.sample(&mut rng))
+            .all(|g| (1.0..3f64.exp()).contains(&g)));
+
+        // Decreasing in u, the exponential distribution
+        let dist: ExprDist<f64> = ExprDist::parse("-ln(u) / rate", " rate = 2 ").unwrap();
+        let exponential: ExponentialDist<f64> = ExponentialDist::new(2.0);
+        for u in [0.1, 0.5, 0.9] {
+            assert_relative_eq!(dist.quantile(u), exponential.quantile(u), epsilon = 1e-12);
+        }
+        assert_relative_eq!(dist.mean() / exponential.mean(), 1.0, epsilon = 1e-3);
+        for g in [0.1, 0.5, 2.0] {
+            assert_relative_eq!(dist.cdf(g), exponential.cdf(g), epsilon = 1e-12);
+            assert_relative_eq!(dist.pdf(g), exponential.pdf(g), epsilon = 1e-10);
+        }
+
+        // Slopes of the other functions
+        let dist: ExprDist<f64> =
+            ExprDist::parse("pow(u, 2) + sqrt(u) + erf(u) + 2^u", "").unwrap();
+        let slope = |u: f64| {
+            2.0 * u + 0.5 / u.sqrt() + 2.0 / PI.sqrt() * (-u * u).exp() + 2f64.ln() * 2f64.powf(u)
+        };
+        for u in [0.1, 0.5, 0.9] {
+            assert_relative_eq!(dist.pdf(dist.quantile(u)), 1.0 / slope(u), epsilon = 1e-9);
+        }
+
+        for (text, values) in [
+            ("exp(a*u)", ""),
+            ("exp(a*u)", "a=1,a=2"),
+            ("exp(a*u)", "a=1,b=2"),
+            ("exp(a*u)", "a=x"),
+            ("exp(a*u)", "a"),
+            ("u - 0.5", ""),
+            ("u * (1 - u)", ""),
+            ("0 / (u - u)", ""),
+            ("ln(u)", ""),
+        ] {
+            assert!(
+                ExprDist::<f64>::parse(text, values).is_err(),
+                "{text} {values}"
+            );
+        }
+    }
+}
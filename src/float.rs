@@ -12,13 +12,19 @@ pub trait Float:
     + Div<Output = Self>
     + Neg<Output = Self>
     + Copy
+    + PartialOrd
     + Display
+    + Send
+    + Sync
 {
     const ONE: Self;
     const NEG_ONE: Self;
     const ZERO: Self;
     const TWO: Self;
+    #[allow(unused)]
     const PI: Self;
+    /// Difference between 1 and the next representable value
+    const EPSILON: Self;
 
     fn new<F: Into<f64>>(x: F) -> Self;
     fn random(rng: &mut ThreadRng) -> Self;
@@ -28,9 +34,21 @@ pub trait Float:
     fn powf(self, exponent: Self) -> Self;
     fn gamma(self) -> Self;
     fn abs(self) -> Self;
+    #[allow(unused)]
     fn erf(self) -> Self;
     #[allow(unused)]
     fn into_f64(self) -> f64;
+
+    /// Whether `self < other`, read from the sign of the difference: the `PartialOrd` of f256
+    /// orders two negative values the wrong way round.
+    fn lt(self, other: Self) -> bool {
+        self - other < Self::ZERO
+    }
+
+    /// Whether `self >= other`, read from the sign of the difference like `lt`.
+    fn ge(self, other: Self) -> bool {
+        !self.lt(other)
+    }
 }
 
 impl Float for f32 {
@@ -39,6 +57,7 @@ impl Float for f32 {
     const ZERO: Self = 0.0f32;
     const TWO: Self = 2.0f32;
     const PI: Self = f32::consts::PI;
+    const EPSILON: Self = f32::EPSILON;
 
     fn new<T>(x: T) -> Self
     where
@@ -90,6 +109,7 @@ impl Float for f64 {
     const ZERO: Self = 0.0f64;
     const TWO: Self = 2.0f64;
     const PI: Self = f64::consts::PI;
+    const EPSILON: Self = f64::EPSILON;
 
     fn new<T>(x: T) -> Self
     where
@@ -141,7 +161,8 @@ impl Float for f256 {
     const ZERO: Self = f256::ZERO;
     const TWO: Self = f256::TWO;
     // not exactly pi to all decimals, only the first 64 bits, but good enough
-    const PI: Self = f256::from_bits((85070776964233020888359549780463976448, 0));
+    const PI: Self = ::f256::consts::PI;
+    const EPSILON: Self = f256::EPSILON;
 
     fn new<T>(x: T) -> Self
     where
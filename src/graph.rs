@@ -0,0 +1,531 @@
+//! Resistor networks on arbitrary graphs.
+//!
+//! A network is read from an edge list with a bond on every line: the ids of its two nodes
+//! followed by its conductance, given as a number, as a distribution `name:param` or
+//! `name(key=value,...)` without spaces, or as `*` for the distribution of the run. Node ids are
+//! any words but `high` and `low`, which stand for the buses held at `V_HGH` and `V_LOW`. Empty
+//! lines and lines starting with `#` are skipped. For example, two branches between the buses,
+//! one of them with a fixed bond:
+//!
+//! ```text
+//! # a    b    conductance
+//! high   n1   *
+//! n1     low  weibull:2
+//! high   n2   0.5
+//! n2     low  *
+//! ```
+//!
+//! The nodes are numbered in order of first appearance. The Laplacian is sparse and is solved by
+//! conjugate gradients, leaving out the clusters cut off from the buses as the lattice solvers
+//! do.
+use crate::config::{Precision, N_TRIES};
+use crate::dists::{Distribution, ValidDists};
+use crate::electrodes::read_spec;
+use crate::float::Float;
+use crate::io;
+use crate::lattice::Bond;
+use crate::percolation;
+use crate::solver::ExportMode;
+use crate::sparse::{conjugate_gradient, CsrMatrix};
+use crate::starmesh;
+use rand::rngs::ThreadRng;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Settings of a run of `compute_graph`.
+pub struct GraphOptions {
+    /// Relative residual at which the conjugate gradient stops
+    pub tolerance: f64,
+    pub export_mode: ExportMode,
+    pub isosurface_value: f64,
+    /// Cross-check every solution against the star-mesh reduction
+    pub cross_check: bool,
+}
+
+/// Conductance of a bond of the edge list.
+enum Conductance {
+    Fixed(Precision),
+    /// Drawn from the distribution of the run
+    Run,
+    /// Drawn from the distribution of this index in `Network::dists`
+    Tagged(usize),
+}
+
+pub struct Network {
+    /// Name of the edge list, used in file names
+    pub name: String,
+    /// Ids of the nodes, by index
+    pub ids: Vec<String>,
+    pub bonds: Vec<Bond>,
+    conductances: Vec<Conductance>,
+    /// Distributions named in the edge list, with their specifications
+    dists: Vec<(String, Box<dyn Distribution<Precision>>)>,
+}
+
+enum Endpoint {
+    High,
+    Low,
+    Node(usize),
+}
+
+impl Network {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Network, String> {
+        let (name, text) = read_spec(path)?;
+        Network::parse(&name, &text)
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Network, String> {
+        let mut network = Network {
+            name: name.to_string(),
+            ids: Vec::new(),
+            bonds: Vec::new(),
+            conductances: Vec::new(),
+            dists: Vec::new(),
+        };
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        let lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+        for (n, line) in lines {
+            let err = |e: String| format!("Line {}: {}", n + 1, e);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(err(format!(
+                    "Expected two nodes and a conductance, found {} fields",
+                    fields.len()
+                )));
+            }
+
+            let mut endpoint = |id: &str| match id {
+                "high" => Endpoint::High,
+                "low" => Endpoint::Low,
+                _ => Endpoint::Node(*index.entry(id.to_string()).or_insert_with(|| {
+                    network.ids.push(id.to_string());
+                    network.ids.len() - 1
+                })),
+            };
+            let bond = match (endpoint(fields[0]), endpoint(fields[1])) {
+                (Endpoint::Node(a), Endpoint::Node(b)) if a == b => {
+                    return Err(err(format!("Bond from node {} to itself", fields[0])))
+                }
+                (Endpoint::Node(a), Endpoint::Node(b)) => Bond::Internal(a, b),
+                (Endpoint::Node(a), Endpoint::High) | (Endpoint::High, Endpoint::Node(a)) => {
+                    Bond::High(a)
+                }
+                (Endpoint::Node(a), Endpoint::Low) | (Endpoint::Low, Endpoint::Node(a)) => {
+                    Bond::Low(a)
+                }
+                _ => return Err(err(String::from("A bond must end at a node"))),
+            };
+
+            let conductance = network.parse_conductance(fields[2]).map_err(err)?;
+            network.bonds.push(bond);
+            network.conductances.push(conductance);
+        }
+
+        let touches = |f: fn(&Bond) -> bool| network.bonds.iter().any(f);
+        if !touches(|b| matches!(b, Bond::High(_))) || !touches(|b| matches!(b, Bond::Low(_))) {
+            return Err(String::from("The network must be joined to both buses"));
+        }
+
+        Ok(network)
+    }
+
+    fn parse_conductance(&mut self, field: &str) -> Result<Conductance, String> {
+        if field == "*" {
+            return Ok(Conductance::Run);
+        }
+        if field.contains(':') {
+            if let Some(k) = self.dists.iter().position(|(spec, _)| spec == field) {
+                return Ok(Conductance::Tagged(k));
+            }
+            let dist = ValidDists::<Precision>::from_spec(field)?.boxed();
+            self.dists.push((field.to_string(), dist));
+            return Ok(Conductance::Tagged(self.dists.len() - 1));
+        }
+
+        let g = field
+            .parse::<f64>()
+            .map_err(|e| format!("Invalid conductance {}: {}", field, e))?;
+        if g < 0.0 {
+            return Err(format!("Negative conductance {}", field));
+        }
+        Ok(Conductance::Fixed(Precision::new(g)))
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Conductances of the bonds, drawing the random ones from their distributions.
+    pub fn sample<D: Distribution<Precision>>(
+        &self,
+        dist: &D,
+        rng: &mut ThreadRng,
+    ) -> Vec<Precision> {
+        self.conductances
+            .iter()
+            .map(|c| match c {
+                Conductance::Fixed(g) => *g,
+                Conductance::Run => dist.sample(rng),
+                Conductance::Tagged(k) => self.dists[*k].1.sample(rng),
+            })
+            .collect()
+    }
+}
+
+/// Potentials of a network of `n` nodes joined by `bonds` of conductances `resist`, and the
+/// number of conjugate gradient iterations it took. Clusters joined to neither bus are left at
+/// zero potential.
+pub fn solve<T, B>(n: usize, bonds: B, resist: &[T], tol: T) -> Result<(Vec<T>, usize), String>
+where
+    T: Float,
+    B: Iterator<Item = Bond> + Clone,
+{
+    let mut triplets: Vec<(usize, usize, T)> = Vec::with_capacity(4 * resist.len());
+    let mut b: Vec<T> = vec![T::ZERO; n];
+
+    let active = percolation::connected(n, bonds.clone(), resist, |_| false);
+    triplets.extend((0..n).filter(|&p| !active[p]).map(|p| (p, p, T::ONE)));
+
+    // The Laplacian itself, which unlike MATRIX is positive definite
+    for (bond, &g) in bonds.zip(resist) {
+        match bond {
+            Bond::Internal(p, _) if !active[p] => continue,
+            Bond::Internal(p, q) => {
+                triplets.extend([(p, p, g), (q, q, g), (p, q, -g), (q, p, -g)]);
+            }
+            Bond::High(p) => {
+                triplets.push((p, p, g));
+                b[p] = b[p] + g * T::ONE;
+            }
+            Bond::Low(p) => {
+                triplets.push((p, p, g));
+                b[p] = b[p] + g * T::NEG_ONE;
+            }
+        }
+    }
+
+    let a = CsrMatrix::from_triplets(n, triplets);
+    let mut x: Vec<T> = vec![T::ZERO; n];
+    let iters = conjugate_gradient(&a, &b, &mut x, tol, 10 * n)?;
+    Ok((x, iters))
+}
+
+/// Effective conductance between the buses, from the current entering through the high one.
+pub fn conductance<T, B>(bonds: B, resist: &[T], x: &[T]) -> T
+where
+    T: Float,
+    B: Iterator<Item = Bond>,
+{
+    let current = bonds
+        .zip(resist)
+        .filter_map(|(bond, &g)| match bond {
+            Bond::High(p) => Some(g * (T::ONE - x[p])),
+            _ => None,
+        })
+        .fold(T::ZERO, |a, b| a + b);
+
+    current / T::TWO
+}
+
+/// Nodes below `value` joined to a node above it, skipping the nodes that are not `active`.
+pub fn isosurface<B>(
+    n: usize,
+    bonds: B,
+    x: &[Precision],
+    value: Precision,
+    active: &[bool],
+) -> Vec<usize>
+where
+    B: Iterator<Item = Bond>,
+{
+    let mut on_surface: Vec<bool> = vec![false; n];
+
+    for bond in bonds {
+        if let Bond::Internal(a, b) = bond {
+            if !active[a] || !active[b] {
+                continue;
+            }
+            if x[a].lt(value) && x[b].ge(value) {
+                on_surface[a] = true;
+            } else if x[b].lt(value) && x[a].ge(value) {
+                on_surface[b] = true;
+            }
+        }
+    }
+
+    (0..n).filter(|&p| on_surface[p]).collect()
+}
+
+/// Solution of a sample of a network, with what the run modes report about it.
+pub struct Solution {
+    pub x: Vec<Precision>,
+    /// Number of conjugate gradient iterations
+    pub iters: usize,
+    pub g: Precision,
+    /// Nodes joined to a bus
+    pub active: Vec<bool>,
+    /// Nodes of the isosurface
+    pub surface: Vec<usize>,
+    /// Net current into every node joined to a bus, zero where Kirchhoff's law holds
+    pub residual: Vec<Precision>,
+}
+
+impl Solution {
+    /// Note on the nodes cut off from the buses for the log, if there are any.
+    pub fn isolated(&self) -> String {
+        let n_isolated = self.active.iter().filter(|&&a| !a).count();
+        if n_isolated > 0 {
+            format!("  isolated={}", n_isolated)
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn err2(&self) -> Precision {
+        self.residual
+            .iter()
+            .fold(Precision::ZERO, |a, &r| a + r * r)
+            .sqrt()
+    }
+
+    pub fn errinf(&self) -> Precision {
+        self.residual
+            .iter()
+            .map(|r| r.abs())
+            .fold(Precision::ZERO, |a, r| if a.lt(r) { r } else { a })
+    }
+}
+
+/// Net current into every node for potentials `x`, zero for the nodes that are not `active`.
+pub fn residual<T, B>(n: usize, bonds: B, resist: &[T], x: &[T], active: &[bool]) -> Vec<T>
+where
+    T: Float,
+    B: Iterator<Item = Bond>,
+{
+    let mut current: Vec<T> = vec![T::ZERO; n];
+
+    for (bond, &g) in bonds.zip(resist) {
+        match bond {
+            Bond::Internal(p, q) => {
+                let i = g * (x[q] - x[p]);
+                current[p] = current[p] + i;
+                current[q] = current[q] - i;
+            }
+            Bond::High(p) => current[p] = current[p] + g * (T::ONE - x[p]),
+            Bond::Low(p) => current[p] = current[p] + g * (T::NEG_ONE - x[p]),
+        }
+    }
+
+    for (i, &a) in current.iter_mut().zip(active) {
+        if !a {
+            *i = T::ZERO;
+        }
+    }
+    current
+}
+
+/// Solves a network of `n` nodes and finds its conductance and the isosurface at `value`.
+pub fn solve_sample(
+    n: usize,
+    bonds: &[Bond],
+    resist: &[Precision],
+    tol: Precision,
+    value: Precision,
+) -> Result<Solution, String> {
+    let (x, iters) = solve(n, bonds.iter().copied(), resist, tol)?;
+    let g = conductance(bonds.iter().copied(), resist, &x);
+    let active = percolation::connected(n, bonds.iter().copied(), resist, |_| false);
+    let surface = isosurface(n, bonds.iter().copied(), &x, value, &active);
+    let residual = residual(n, bonds.iter().copied(), resist, &x, &active);
+
+    Ok(Solution {
+        x,
+        iters,
+        g,
+        active,
+        surface,
+        residual,
+    })
+}
+
+pub fn compute_graph<D, P>(dist: D, network: &Network, outdir: P, options: GraphOptions)
+where
+    D: Distribution<Precision>,
+    P: AsRef<Path>,
+{
+    let outdir: &Path = outdir.as_ref();
+    let mut rng = rand::rng();
+    let n = network.n_nodes();
+    let tol = Precision::new(options.tolerance);
+    let value = Precision::new(options.isosurface_value);
+
+    for iter in 0..N_TRIES {
+        let resist = network.sample(&dist, &mut rng);
+        let (duration, result) = timeit!(solve_sample(n, &network.bonds, &resist, tol, value));
+
+        let solution = match result {
+            Ok(solution) => solution,
+            Err(e) => {
+                println!(
+                    "{}/{}  {}  dist={}  FAIL - {}",
+                    iter + 1,
+                    N_TRIES,
+                    network.name,
+                    dist,
+                    e
+                );
+                continue;
+            }
+        };
+        let g = solution.g;
+
+        let check = if options.cross_check {
+            let g_mesh = starmesh::network_conductance(n, network.bonds.iter().copied(), &resist);
+            format!("  |dG|/G={:<9.3e}", ((g - g_mesh) / g).abs())
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{}/{}  {}  dist={}  time={:>5.3}s  iters={}  G={:<9.3e}{}{}  surface={}",
+            iter + 1,
+            N_TRIES,
+            network.name,
+            dist,
+            duration.as_secs_f64(),
+            solution.iters,
+            g,
+            check,
+            solution.isolated(),
+            solution.surface.len()
+        );
+
+        let out_file = outdir.join(format!("graph_{}_{}.out", network.name, dist));
+        io::append_line(&out_file, &format!("{} {}", g, solution.surface.len()))
+            .expect("Failed at saving results");
+
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportArrays | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            io::export_graph_arrays(&dist, network, outdir, &resist, &solution.x, iter)
+                .expect("Failed at saving results");
+        }
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportIsoSurface | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            let surf_file = outdir.join(format!("isosurfaces_{}_{}.out", network.name, dist));
+            let ids: Vec<&str> = solution
+                .surface
+                .iter()
+                .map(|&p| network.ids[p].as_str())
+                .collect();
+            io::append_line(&surf_file, &ids.join(" ")).expect("Failed at saving results");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_parse() {
+        let text =
+            "# Two branches\n\nhigh n1 *\nn1 low weibull:2\nhigh  n2 0.5\nn2 low weibull:2\n";
+        let network = Network::parse("test", text).unwrap();
+
+        assert_eq!(network.ids, ["n1", "n2"]);
+        assert_eq!(
+            network.bonds,
+            [Bond::High(0), Bond::Low(0), Bond::High(1), Bond::Low(1)]
+        );
+        assert_eq!(network.dists.len(), 1);
+        assert!(
+            matches!(network.conductances[2], Conductance::Fixed(g) if g == Precision::new(0.5))
+        );
+
+        for text in [
+            "high n1 1\n",
+            "n1 low 1\n",
+            "high n1 1\nn1 low\n",
+            "high n1 1\nn1 n1 1\nn1 low 1\n",
+            "high low 1\nhigh n1 1\nn1 low 1\n",
+            "high n1 -1\nn1 low 1\n",
+            "high n1 cauchy:2\nn1 low 1\n",
+        ] {
+            assert!(Network::parse("test", text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_wheatstone_bridge() {
+        // Balanced bridge between the buses, with a dangling pair of nodes cut off from it
+        let text = "high a 1\nhigh b 2\na low 2\nb low 4\na b 3\nc d 1\n";
+        let network = Network::parse("test", text).unwrap();
+        let n = network.n_nodes();
+        let bonds = || network.bonds.iter().copied();
+        let resist: Vec<f64> = network
+            .conductances
+            .iter()
+            .map(|c| match c {
+                Conductance::Fixed(g) => g.into_f64(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        let (x, _) = solve(n, bonds(), &resist, 1e-12).unwrap();
+        // No current through the bridge, so the two branches in parallel
+        let expected = 2.0 / 3.0 + 4.0 / 3.0;
+        assert_relative_eq!(conductance(bonds(), &resist, &x), expected, epsilon = EPS);
+        assert_relative_eq!(
+            starmesh::network_conductance(n, bonds(), &resist),
+            expected,
+            epsilon = EPS
+        );
+        assert_relative_eq!(x[0], x[1], epsilon = EPS);
+
+        let active = percolation::connected(n, bonds(), &resist, |_| false);
+        assert_eq!(active, [true, true, false, false]);
+        for r in residual(n, bonds(), &resist, &x, &active) {
+            assert_relative_eq!(r, 0.0, epsilon = EPS);
+        }
+    }
+
+    #[test]
+    fn test_isosurface() {
+        // A chain high - 0 - 1 - 2 - low, with the zero potential between nodes 1 and 2
+        let bonds = [
+            Bond::High(0),
+            Bond::Internal(0, 1),
+            Bond::Internal(1, 2),
+            Bond::Low(2),
+        ];
+        let x: Vec<Precision> = [0.6, 0.2, -0.2].into_iter().map(Precision::new).collect();
+        let surface = isosurface(3, bonds.into_iter(), &x, Precision::ZERO, &[true; 3]);
+        assert_eq!(surface, [2]);
+        let surface = isosurface(
+            3,
+            bonds.into_iter(),
+            &x,
+            Precision::ZERO,
+            &[true, true, false],
+        );
+        assert!(surface.is_empty());
+
+        // Negative potentials and isovalue, which f256 orders the wrong way round
+        let x: Vec<Precision> = [-0.2, -0.6, -0.9].into_iter().map(Precision::new).collect();
+        let value = Precision::new(-0.7);
+        let surface = isosurface(3, bonds.into_iter(), &x, value, &[true; 3]);
+        assert_eq!(surface, [2]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Lattice Green's function and two-point resistances of a sample.
+//!
+//! The Green's function is the inverse of the Laplacian assembled by `build_system`, in which the
+//! bus bonds tie the first and last columns to the buses, here both taken as ground. Two-point
+//! resistances then follow from the grounded-Laplacian formula R_ab = G_aa + G_bb - 2 G_ab.
+use crate::config::{Precision, N_UNK};
+#[allow(unused_imports)]
+use crate::float::Float;
+use crate::linalg::{lu_factorize, lu_solve};
+use crate::solver::{build_system, MATRIX, ZERO};
+
+/// Factorized Laplacian of a sample, held in `MATRIX` until it is assembled again.
+pub struct GreenFunction {
+    perm: Vec<usize>,
+}
+
+impl GreenFunction {
+    /// Assembles the system of `resist` and factorizes it in place.
+    #[allow(static_mut_refs)]
+    pub unsafe fn new(resist: &[Precision]) -> Result<GreenFunction, String> {
+        build_system(resist);
+        let perm = lu_factorize(&mut MATRIX[..], N_UNK)?;
+        Ok(GreenFunction { perm })
+    }
+
+    /// Potentials of every node for a unit current injected at `source`.
+    #[allow(static_mut_refs)]
+    pub unsafe fn column(&self, source: usize) -> Vec<Precision> {
+        // MATRIX holds minus the Laplacian
+        let mut x: Vec<Precision> = vec![ZERO; N_UNK];
+        x[source] = Precision::NEG_ONE;
+        lu_solve(&MATRIX[..], &self.perm, &mut x);
+        x
+    }
+
+    /// Diagonal entry of the Green's function at `node`, read from the factors without a full
+    /// solve: with P A = L U it is the product of row `node` of U^-1 and column `node` of L^-1 P,
+    /// which both vanish before `node` and its pivoted row.
+    #[allow(static_mut_refs)]
+    pub unsafe fn diagonal(&self, node: usize) -> Precision {
+        let a = &MATRIX[..];
+
+        let mut w: Vec<Precision> = vec![ZERO; N_UNK];
+        w[node] = Precision::ONE / a[node][node];
+        for i in node + 1..N_UNK {
+            let dot = (node..i).fold(ZERO, |acc, k| acc + a[k][i] * w[k]);
+            w[i] = -dot / a[i][i];
+        }
+
+        let start = self.perm.iter().position(|&p| p == node).unwrap();
+        let mut y: Vec<Precision> = vec![ZERO; N_UNK];
+        y[start] = Precision::ONE;
+        for i in start + 1..N_UNK {
+            y[i] = -(start..i).fold(ZERO, |acc, k| acc + a[i][k] * y[k]);
+        }
+
+        // MATRIX holds minus the Laplacian
+        -(node.max(start)..N_UNK).fold(ZERO, |acc, k| acc + w[k] * y[k])
+    }
+
+    /// Effective resistances between `source` and each of `targets`, given the column of `source`.
+    pub unsafe fn resistances_from(
+        &self,
+        source: usize,
+        column: &[Precision],
+        targets: &[usize],
+    ) -> Vec<Precision> {
+        targets
+            .iter()
+            .map(|&t| column[source] + self.diagonal(t) - Precision::TWO * column[t])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LX, LY};
+    use crate::lattice::{bonds, displacement, fidx, Bond};
+    use crate::solver::MATRIX_LOCK;
+    use approx::assert_relative_eq;
+    use rand::Rng;
+
+    const EPS: f64 = 1e-10;
+
+    #[test]
+    fn test_chain_resistances() {
+        // A chain of unit bonds along the first row, grounded at both ends through the bus bonds:
+        // the resistance between two nodes is that of the segment between them in parallel with
+        // the rest of the chain
+        let resist: Vec<Precision> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b) == (0, 1) && a % LX == 0 => {
+                    Precision::ONE
+                }
+                Bond::High(a) | Bond::Low(a) if a % LX == 0 => Precision::ONE,
+                _ => ZERO,
+            })
+            .collect();
+        let n = (LY - 1) as f64;
+        let source = fidx(0, 2);
+        let targets: Vec<usize> = (3..LY - 2).map(|c| fidx(0, c)).collect();
+
+        let _lock = MATRIX_LOCK.lock().unwrap();
+        let resistances = unsafe {
+            let green = GreenFunction::new(&resist).unwrap();
+            let column = green.column(source);
+            green.resistances_from(source, &column, &targets)
+        };
+        for (d, r) in (1..).zip(resistances) {
+            let d = d as f64;
+            assert_relative_eq!(r.into_f64(), d * (n - d) / n, epsilon = EPS);
+        }
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let mut rng = rand::rng();
+        let resist: Vec<Precision> = bonds()
+            .map(|_| Precision::new(0.1 + rng.random::<f64>()))
+            .collect();
+
+        let _lock = MATRIX_LOCK.lock().unwrap();
+        unsafe {
+            let green = GreenFunction::new(&resist).unwrap();
+            for node in [0, fidx(LX / 2, (LY - 2) / 2), N_UNK - 1] {
+                assert_relative_eq!(
+                    green.diagonal(node).into_f64(),
+                    green.column(node)[node].into_f64(),
+                    epsilon = EPS
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Random geometric networks of hopping sites.
+//!
+//! Sites are placed uniformly at random in a rectangle, with energies uniform in `[-1, 1]`, and
+//! joined either to every site closer than a cutoff or along the edges of their Delaunay
+//! triangulation. Two sites a distance `r` apart are joined by the Miller–Abrahams conductance
+//! `g = exp(-2 r / xi - E / kT)`, with `E = (|E_i| + |E_j| + |E_i - E_j|) / 2` the activation
+//! energy of the hop, as in variable-range hopping. The drive is along the length of the
+//! rectangle, with the buses at the Fermi level along its short sides: sites within the contact
+//! distance of one of them hop to it over their distance to the side.
+//!
+//! Positions and energies are redrawn for every sample, and the networks are solved as the
+//! networks of edge lists are. The sites are numbered along the drive, so that the star-mesh
+//! reduction eliminates them in an order of small fill-in.
+use crate::config::{Precision, N_TRIES};
+use crate::float::Float;
+use crate::graph::{self, Solution};
+use crate::io;
+use crate::lattice::Bond;
+use crate::solver::ExportMode;
+use crate::starmesh;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Settings of a run of `compute_hopping`.
+pub struct HoppingOptions {
+    pub n_sites: usize,
+    /// Length along the drive and width of the rectangle
+    pub size: (f64, f64),
+    /// Distance below which sites are joined, or `None` to join them by the Delaunay
+    /// triangulation
+    pub cutoff: Option<f64>,
+    /// Localization length
+    pub xi: f64,
+    /// Temperature, in units of half the width of the energy band
+    pub temperature: f64,
+    /// Relative residual at which the conjugate gradient stops
+    pub tolerance: f64,
+    pub export_mode: ExportMode,
+    pub isosurface_value: f64,
+    /// Cross-check every solution against the star-mesh reduction
+    pub cross_check: bool,
+}
+
+impl HoppingOptions {
+    /// Checks that the options describe a network that can be drawn and solved.
+    pub fn check(&self) -> Result<(), String> {
+        if self.n_sites < 3 {
+            return Err(String::from(
+                "The hopping network needs at least three sites.",
+            ));
+        }
+        let positive = |v: f64| v.is_finite() && v > 0.0;
+        if !positive(self.size.0) || !positive(self.size.1) {
+            return Err(format!(
+                "Rectangle {}x{} of the hopping sites must have a positive size.",
+                self.size.0, self.size.1
+            ));
+        }
+        if !positive(self.xi) {
+            return Err(format!("Localization length {} must be positive.", self.xi));
+        }
+        if !positive(self.temperature) {
+            return Err(format!(
+                "Temperature {} must be positive.",
+                self.temperature
+            ));
+        }
+        if let Some(cutoff) = self.cutoff.filter(|&c| !positive(c)) {
+            return Err(format!("Cutoff {} must be positive.", cutoff));
+        }
+        Ok(())
+    }
+
+    /// Distance from the sides of the rectangle within which sites hop to the buses: the cutoff,
+    /// or the mean distance between sites for Delaunay networks.
+    fn contact(&self) -> f64 {
+        self.cutoff
+            .unwrap_or_else(|| (self.size.0 * self.size.1 / self.n_sites as f64).sqrt())
+    }
+}
+
+impl fmt::Display for HoppingOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hop(N={},{:.1}x{:.1},xi={:.3},kT={:.3}",
+            self.n_sites, self.size.0, self.size.1, self.xi, self.temperature
+        )?;
+        match self.cutoff {
+            Some(cutoff) => write!(f, ",rc={:.3})", cutoff),
+            None => write!(f, ",del)"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Site {
+    pub x: f64,
+    pub y: f64,
+    pub energy: f64,
+}
+
+fn distance(a: Site, b: Site) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// `n` sites in a rectangle of `size`, sorted along the drive.
+pub fn place_sites(n: usize, size: (f64, f64), rng: &mut ThreadRng) -> Vec<Site> {
+    let mut sites: Vec<Site> = (0..n)
+        .map(|_| Site {
+            x: size.0 * rng.random::<f64>(),
+            y: size.1 * rng.random::<f64>(),
+            energy: 2.0 * rng.random::<f64>() - 1.0,
+        })
+        .collect();
+    sites.sort_by(|a, b| a.x.total_cmp(&b.x));
+    sites
+}
+
+/// Pairs of sites closer than `cutoff`, found on a grid of cells as large as the cutoff.
+fn cutoff_pairs(sites: &[Site], size: (f64, f64), cutoff: f64) -> Vec<(usize, usize)> {
+    let n_cells = |len: f64| ((len / cutoff).ceil() as usize).max(1);
+    let (nx, ny) = (n_cells(size.0), n_cells(size.1));
+    let cell = |s: Site| {
+        let cx = ((s.x / cutoff) as usize).min(nx - 1);
+        let cy = ((s.y / cutoff) as usize).min(ny - 1);
+        (cx, cy)
+    };
+
+    let mut cells: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, &s) in sites.iter().enumerate() {
+        cells.entry(cell(s)).or_default().push(i);
+    }
+
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for (i, &s) in sites.iter().enumerate() {
+        let (cx, cy) = cell(s);
+        for nbx in cx.saturating_sub(1)..=(cx + 1).min(nx - 1) {
+            for nby in cy.saturating_sub(1)..=(cy + 1).min(ny - 1) {
+                let Some(others) = cells.get(&(nbx, nby)) else {
+                    continue;
+                };
+                pairs.extend(
+                    others
+                        .iter()
+                        .filter(|&&j| j > i && distance(s, sites[j]) < cutoff)
+                        .map(|&j| (i, j)),
+                );
+            }
+        }
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Circumcentre and squared circumradius of a triangle.
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> ((f64, f64), f64) {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    let (a2, b2, c2) = (
+        a.0 * a.0 + a.1 * a.1,
+        b.0 * b.0 + b.1 * b.1,
+        c.0 * c.0 + c.1 * c.1,
+    );
+    let ux = (a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d;
+    let uy = (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d;
+    let r2 = (a.0 - ux).powi(2) + (a.1 - uy).powi(2);
+    ((ux, uy), r2)
+}
+
+/// Delaunay triangulation of `points`, by the Bowyer–Watson algorithm.
+///
+/// The points are inserted one by one into a triangulation that starts from a triangle
+/// enclosing them all. Every insertion removes the triangles whose circumcircle holds the new
+/// point and joins it to the boundary of the cavity they leave.
+fn triangulate(points: &[(f64, f64)]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(x, y) in points.iter() {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let d = (max.0 - min.0).max(max.1 - min.1).max(1.0);
+    let mid = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+
+    let mut pts = points.to_vec();
+    pts.extend([
+        (mid.0 - 100.0 * d, mid.1 - 100.0 * d),
+        (mid.0, mid.1 + 100.0 * d),
+        (mid.0 + 100.0 * d, mid.1 - 100.0 * d),
+    ]);
+
+    let triangle = |t: [usize; 3]| (t, circumcircle(pts[t[0]], pts[t[1]], pts[t[2]]));
+    let mut triangles = vec![triangle([n, n + 1, n + 2])];
+
+    for (p, &(x, y)) in points.iter().enumerate() {
+        let (bad, good): (Vec<_>, Vec<_>) = triangles
+            .into_iter()
+            .partition(|&(_, ((ux, uy), r2))| (x - ux).powi(2) + (y - uy).powi(2) < r2);
+
+        // The boundary of the cavity is made of the edges of a single removed triangle
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for (t, _) in bad.iter() {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        triangles = good;
+        triangles.extend(
+            edges
+                .into_iter()
+                .filter(|&(_, count)| count == 1)
+                .map(|((a, b), _)| triangle([a, b, p])),
+        );
+    }
+
+    triangles
+        .into_iter()
+        .map(|(t, _)| t)
+        .filter(|t| t.iter().all(|&v| v < n))
+        .collect()
+}
+
+/// Edges of the Delaunay triangulation of `points`.
+fn delaunay(points: &[(f64, f64)]) -> Vec<(usize, usize)> {
+    let mut edges: Vec<(usize, usize)> = triangulate(points)
+        .into_iter()
+        .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[0], t[2])])
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// Miller–Abrahams conductance of a hop over a distance `r` between sites of energies `ei` and
+/// `ej`.
+fn miller_abrahams(r: f64, ei: f64, ej: f64, xi: f64, temperature: f64) -> Precision {
+    let activation = (ei.abs() + ej.abs() + (ei - ej).abs()) / 2.0;
+    Precision::new(-2.0 * r / xi - activation / temperature).exp()
+}
+
+/// Bonds of the network of `sites` and their conductances.
+pub fn build_network(sites: &[Site], options: &HoppingOptions) -> (Vec<Bond>, Vec<Precision>) {
+    let pairs = match options.cutoff {
+        Some(cutoff) => cutoff_pairs(sites, options.size, cutoff),
+        None => {
+            let points: Vec<(f64, f64)> = sites.iter().map(|s| (s.x, s.y)).collect();
+            delaunay(&points)
+        }
+    };
+    let (xi, kt) = (options.xi, options.temperature);
+
+    let mut bonds: Vec<Bond> = Vec::with_capacity(pairs.len());
+    let mut resist: Vec<Precision> = Vec::with_capacity(pairs.len());
+    for (i, j) in pairs {
+        let (a, b) = (sites[i], sites[j]);
+        bonds.push(Bond::Internal(i, j));
+        resist.push(miller_abrahams(distance(a, b), a.energy, b.energy, xi, kt));
+    }
+
+    // The buses are at the Fermi level
+    let contact = options.contact();
+    for (i, s) in sites.iter().enumerate() {
+        if s.x < contact {
+            bonds.push(Bond::High(i));
+            resist.push(miller_abrahams(s.x, s.energy, 0.0, xi, kt));
+        }
+        if options.size.0 - s.x < contact {
+            bonds.push(Bond::Low(i));
+            resist.push(miller_abrahams(options.size.0 - s.x, s.energy, 0.0, xi, kt));
+        }
+    }
+
+    (bonds, resist)
+}
+
+pub fn compute_hopping<P: AsRef<Path>>(outdir: P, options: HoppingOptions) {
+    let outdir: &Path = outdir.as_ref();
+    let mut rng = rand::rng();
+    let n = options.n_sites;
+    let tol = Precision::new(options.tolerance);
+    let value = Precision::new(options.isosurface_value);
+
+    for iter in 0..N_TRIES {
+        let sites = place_sites(n, options.size, &mut rng);
+        let (bonds, resist) = build_network(&sites, &options);
+        let (duration, result) = timeit!(graph::solve_sample(n, &bonds, &resist, tol, value));
+
+        let solution: Solution = match result {
+            Ok(solution) => solution,
+            Err(e) => {
+                println!("{}/{}  {}  FAIL - {}", iter + 1, N_TRIES, options, e);
+                continue;
+            }
+        };
+        let g = solution.g;
+
+        let check = if options.cross_check {
+            let g_mesh = starmesh::network_conductance(n, bonds.iter().copied(), &resist);
+            format!("  |dG|/G={:<9.3e}", ((g - g_mesh) / g).abs())
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{}/{}  {}  time={:>5.3}s  bonds={}  iters={}  G={:<9.3e}{}{}  surface={}",
+            iter + 1,
+            N_TRIES,
+            options,
+            duration.as_secs_f64(),
+            bonds.len(),
+            solution.iters,
+            g,
+            check,
+            solution.isolated(),
+            solution.surface.len()
+        );
+
+        let out_file = outdir.join(format!("hopping_{}.out", options));
+        io::append_line(&out_file, &format!("{} {}", g, solution.surface.len()))
+            .expect("Failed at saving results");
+
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportArrays | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            io::export_hopping_arrays(&options, outdir, &sites, &solution.x, iter)
+                .expect("Failed at saving results");
+        }
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportIsoSurface | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            let surf_file = outdir.join(format!("isosurfaces_{}.out", options));
+            let line = solution
+                .surface
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(" ");
+            io::append_line(&surf_file, &line).expect("Failed at saving results");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn options(cutoff: Option<f64>) -> HoppingOptions {
+        HoppingOptions {
+            n_sites: 200,
+            size: (10.0, 10.0),
+            cutoff,
+            xi: 1.0,
+            temperature: 1.0,
+            tolerance: 1e-12,
+            export_mode: ExportMode::NoExport,
+            isosurface_value: 0.0,
+            cross_check: false,
+        }
+    }
+
+    #[test]
+    fn test_cutoff_pairs() {
+        let mut rng = rand::rng();
+        let sites = place_sites(200, (10.0, 5.0), &mut rng);
+
+        let mut expected: Vec<(usize, usize)> = Vec::new();
+        for i in 0..sites.len() {
+            for j in i + 1..sites.len() {
+                if distance(sites[i], sites[j]) < 1.5 {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert_eq!(cutoff_pairs(&sites, (10.0, 5.0), 1.5), expected);
+    }
+
+    #[test]
+    fn test_delaunay() {
+        // The corners of a square around a point off its centre
+        let points = [(0.0, 0.0), (2.0, 0.0), (0.0, 2.0), (2.0, 2.0), (0.9, 1.2)];
+        let edges = delaunay(&points);
+        let expected = [
+            (0, 1),
+            (0, 2),
+            (0, 4),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        assert_eq!(edges, expected);
+
+        // No point lies inside the circumcircle of a triangle of a random triangulation
+        let mut rng = rand::rng();
+        let points: Vec<(f64, f64)> = (0..100)
+            .map(|_| (rng.random::<f64>(), rng.random::<f64>()))
+            .collect();
+        let triangles = triangulate(&points);
+        assert!(delaunay(&points).len() <= 3 * points.len() - 6);
+        for t in triangles {
+            let ((ux, uy), r2) = circumcircle(points[t[0]], points[t[1]], points[t[2]]);
+            for (p, &(x, y)) in points.iter().enumerate() {
+                if !t.contains(&p) {
+                    assert!((x - ux).powi(2) + (y - uy).powi(2) > r2 * (1.0 - 1e-9));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_miller_abrahams() {
+        assert_relative_eq!(
+            miller_abrahams(1.5, 0.2, -0.3, 1.0, 0.5).into_f64(),
+            (-3.0 - 0.5 / 0.5f64).exp(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            miller_abrahams(1.0, 0.2, 0.5, 2.0, 1.0).into_f64(),
+            (-1.0 - 0.5f64).exp(),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_hopping_network() {
+        let mut rng = rand::rng();
+        for cutoff in [Some(2.0), None] {
+            let options = options(cutoff);
+            let sites = place_sites(options.n_sites, options.size, &mut rng);
+            let (bonds, resist) = build_network(&sites, &options);
+            let n = options.n_sites;
+
+            let solution =
+                graph::solve_sample(n, &bonds, &resist, Precision::new(1e-12), Precision::ZERO)
+                    .unwrap();
+            let g_mesh = starmesh::network_conductance(n, bonds.iter().copied(), &resist);
+            assert!(solution.g > Precision::ZERO);
+            assert_relative_eq!(
+                solution.g.into_f64(),
+                g_mesh.into_f64(),
+                max_relative = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(options(None).check().is_ok());
+        let invalid = [
+            HoppingOptions {
+                n_sites: 2,
+                ..options(None)
+            },
+            HoppingOptions {
+                size: (10.0, 0.0),
+                ..options(None)
+            },
+            HoppingOptions {
+                xi: 0.0,
+                ..options(None)
+            },
+            HoppingOptions {
+                xi: f64::NAN,
+                ..options(None)
+            },
+            HoppingOptions {
+                temperature: -1.0,
+                ..options(None)
+            },
+            options(Some(0.0)),
+        ];
+        assert!(invalid.iter().all(|o| o.check().is_err()));
+    }
+}
@@ -0,0 +1,401 @@
+//! Conductance maps from grayscale images.
+//!
+//! A PGM or PNG image is read as a map of the `LX x LY` grid of sites of the lattice, its rows
+//! along the rows of the lattice and its columns across the drive, so that the first and last
+//! columns of the grid are the buses. Images of another size are resampled to the nearest pixel.
+//! The intensities, scaled to `[0, 1]`, are turned into the conductances of the sites by a
+//! monotone mapping, and every bond joins the halves of its two sites in series, taking the
+//! harmonic mean of their conductances. The network is the same for every sample.
+use crate::config::{Precision, LX, LY};
+use crate::dists::Distribution;
+use crate::electrodes::read_spec;
+use crate::float::Float;
+use crate::lattice::{bonds, coords, Bond};
+use image::{Rgb, RgbImage};
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::fmt;
+use std::path::Path;
+
+/// Monotone mapping from intensities in `[0, 1]` to conductances.
+pub enum Mapping {
+    /// `g = lo + (hi - lo) u`
+    Linear { lo: f64, hi: f64 },
+    /// `g = lo (hi / lo)^u`, spreading the intensities over decades
+    Log { lo: f64, hi: f64 },
+    /// `g = scale u^exponent`
+    Power { scale: f64, exponent: f64 },
+    /// Linear interpolation between the points `(u, g)` of a table, constant beyond its ends
+    Table {
+        name: String,
+        points: Vec<(f64, f64)>,
+    },
+}
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    text.parse::<f64>()
+        .map_err(|e| format!("Invalid number {}: {}", text, e))
+}
+
+impl Mapping {
+    /// Parses `linear:lo:hi`, `log:lo:hi`, `power:scale:exponent` or `table:FILE`, the file
+    /// holding a point `u g` on every line.
+    pub fn parse(spec: &str) -> Result<Mapping, String> {
+        let fields: Vec<&str> = spec.split(':').collect();
+        let mapping = match fields[..] {
+            ["linear", lo, hi] => Mapping::Linear {
+                lo: parse_number(lo)?,
+                hi: parse_number(hi)?,
+            },
+            ["log", lo, hi] => Mapping::Log {
+                lo: parse_number(lo)?,
+                hi: parse_number(hi)?,
+            },
+            ["power", scale, exponent] => Mapping::Power {
+                scale: parse_number(scale)?,
+                exponent: parse_number(exponent)?,
+            },
+            ["table", path] => {
+                let (name, text) = read_spec(path)?;
+                Mapping::table(&name, &text)?
+            }
+            _ => return Err(format!("Mapping {} not supported.", spec)),
+        };
+
+        match mapping {
+            Mapping::Linear { lo, hi } if lo < 0.0 || hi < 0.0 => Err(String::from(
+                "Linear mappings need conductances of at least zero",
+            )),
+            Mapping::Log { lo, hi } if lo <= 0.0 || hi <= 0.0 => {
+                Err(String::from("Log mappings need positive conductances"))
+            }
+            Mapping::Power { scale, exponent } if scale < 0.0 || exponent <= 0.0 => {
+                Err(String::from(
+                    "Power mappings need a scale of at least zero and a positive exponent",
+                ))
+            }
+            _ => Ok(mapping),
+        }
+    }
+
+    /// Table mapping from the points `u g` on the lines of `text`, which must increase in `u`
+    /// and be monotone in `g`.
+    pub fn table(name: &str, text: &str) -> Result<Mapping, String> {
+        let mut points: Vec<(f64, f64)> = Vec::new();
+
+        let lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+        for (n, line) in lines {
+            let err = |e: String| format!("Line {}: {}", n + 1, e);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [u, g] = fields[..] else {
+                return Err(err(String::from("Expected an intensity and a conductance")));
+            };
+            let (u, g) = (parse_number(u).map_err(err)?, parse_number(g).map_err(err)?);
+            if g < 0.0 {
+                return Err(err(format!("Negative conductance {}", g)));
+            }
+            points.push((u, g));
+        }
+
+        if points.len() < 2 {
+            return Err(String::from("A table needs at least two points"));
+        }
+        if points.windows(2).any(|w| w[1].0 <= w[0].0) {
+            return Err(String::from("The intensities of a table must increase"));
+        }
+        let rising = points.windows(2).all(|w| w[1].1 >= w[0].1);
+        let falling = points.windows(2).all(|w| w[1].1 <= w[0].1);
+        if !rising && !falling {
+            return Err(String::from("The conductances of a table must be monotone"));
+        }
+
+        Ok(Mapping::Table {
+            name: name.to_string(),
+            points,
+        })
+    }
+
+    pub fn apply(&self, u: f64) -> f64 {
+        match self {
+            Mapping::Linear { lo, hi } => lo + (hi - lo) * u,
+            Mapping::Log { lo, hi } => lo * (hi / lo).powf(u),
+            Mapping::Power { scale, exponent } => scale * u.powf(*exponent),
+            Mapping::Table { points, .. } => {
+                let k = points.partition_point(|&(x, _)| x <= u);
+                if k == 0 {
+                    return points[0].1;
+                }
+                if k == points.len() {
+                    return points[k - 1].1;
+                }
+                let ((u0, g0), (u1, g1)) = (points[k - 1], points[k]);
+                g0 + (g1 - g0) * (u - u0) / (u1 - u0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mapping::Linear { lo, hi } => write!(f, "lin({:.3e},{:.3e})", lo, hi),
+            Mapping::Log { lo, hi } => write!(f, "log({:.3e},{:.3e})", lo, hi),
+            Mapping::Power { scale, exponent } => write!(f, "pow({:.3e},{:.3})", scale, exponent),
+            Mapping::Table { name, .. } => write!(f, "table({})", name),
+        }
+    }
+}
+
+/// Grayscale image with intensities scaled to `[0, 1]`.
+#[derive(Clone)]
+pub struct Grayscale {
+    /// Name of the image, used in file names
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    /// Intensities, row by row
+    pub pixels: Vec<f64>,
+}
+
+impl Grayscale {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Grayscale, String> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?
+            .into_luma16();
+        let name = path
+            .file_stem()
+            .map_or(String::from("image"), |s| s.to_string_lossy().into_owned());
+
+        Ok(Grayscale {
+            name,
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: image.pixels().map(|p| p.0[0] as f64 / 65535.0).collect(),
+        })
+    }
+
+    /// Intensity at the site of the grid at `row` and `col`, the high bus being column zero.
+    fn site(&self, row: usize, col: usize) -> f64 {
+        let y = (2 * row + 1) * self.height / (2 * LX);
+        let x = (2 * col + 1) * self.width / (2 * LY);
+        self.pixels[y * self.width + x]
+    }
+
+    /// Writes the image in color with the nodes of `surface` marked in red.
+    pub fn export_overlay(&self, surface: &[(usize, usize)], path: &Path) -> Result<(), String> {
+        let mut marked: Vec<bool> = vec![false; LX * LY];
+        for &(row, col) in surface.iter() {
+            marked[row * LY + col + 1] = true;
+        }
+
+        let overlay = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let row = (2 * y as usize + 1) * LX / (2 * self.height);
+            let col = (2 * x as usize + 1) * LY / (2 * self.width);
+            if marked[row * LY + col] {
+                Rgb([255, 0, 0])
+            } else {
+                let v = (self.pixels[y as usize * self.width + x as usize] * 255.0).round() as u8;
+                Rgb([v, v, v])
+            }
+        });
+
+        overlay
+            .save(path)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
+
+/// Conductances of the lattice read from an image.
+pub struct ImageMap {
+    image: Grayscale,
+    mapping: Mapping,
+    /// Conductance of every bond, in the order of `bonds`
+    resist: Vec<Precision>,
+    /// The same conductances in increasing order
+    sorted: Vec<Precision>,
+}
+
+/// Two conductances in series over half a bond each.
+fn series(a: f64, b: f64) -> f64 {
+    if a + b == 0.0 {
+        0.0
+    } else {
+        2.0 * a * b / (a + b)
+    }
+}
+
+impl ImageMap {
+    pub fn with(image: Grayscale, mapping: Mapping) -> ImageMap {
+        let site = |row: usize, col: usize| mapping.apply(image.site(row, col));
+        let node = |idx: usize| {
+            let (row, col) = coords(idx);
+            site(row, col + 1)
+        };
+
+        let resist: Vec<Precision> = bonds()
+            .map(|bond| {
+                let g = match bond {
+                    Bond::Internal(a, b) => series(node(a), node(b)),
+                    Bond::High(a) => series(site(coords(a).0, 0), node(a)),
+                    Bond::Low(a) => series(node(a), site(coords(a).0, LY - 1)),
+                };
+                Precision::new(g)
+            })
+            .collect();
+
+        let mut sorted = resist.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        ImageMap {
+            image,
+            mapping,
+            resist,
+            sorted,
+        }
+    }
+}
+
+impl fmt::Display for ImageMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "img({},{})", self.image.name, self.mapping)
+    }
+}
+
+impl Distribution<Precision> for ImageMap {
+    /// A single value is the conductance of a bond of the map picked at random.
+    fn sample(&self, rng: &mut ThreadRng) -> Precision {
+        self.resist[rng.random_range(0..self.resist.len())]
+    }
+
+    /// The conductances of the map are discrete values.
+    fn pdf(&self, _x: Precision) -> Precision {
+        Precision::ZERO
+    }
+
+    fn cdf(&self, x: Precision) -> Precision {
+        let k = self.sorted.partition_point(|&g| x.ge(g));
+        Precision::new(k as f64 / self.sorted.len() as f64)
+    }
+
+    fn quantile(&self, u: Precision) -> Precision {
+        let k = (u.into_f64() * self.sorted.len() as f64) as usize;
+        self.sorted[k.min(self.sorted.len() - 1)]
+    }
+
+    fn fill(&self, _rng: &mut ThreadRng, resist: &mut [Precision]) {
+        resist.copy_from_slice(&self.resist);
+    }
+
+    #[allow(unused)]
+    fn mean(&self) -> Precision {
+        let total = self.resist.iter().fold(Precision::ZERO, |acc, &g| acc + g);
+        total / Precision::new(self.resist.len() as f64)
+    }
+
+    fn variance(&self) -> Precision {
+        let mean = self.mean();
+        self.resist
+            .iter()
+            .fold(Precision::ZERO, |acc, &g| acc + (g - mean) * (g - mean))
+            / Precision::new(self.resist.len() as f64)
+    }
+
+    /// Diverges to minus infinity if some of the bonds do not conduct.
+    fn mean_log(&self) -> Precision {
+        let total = self
+            .resist
+            .iter()
+            .fold(Precision::ZERO, |acc, &g| acc + g.ln());
+        total / Precision::new(self.resist.len() as f64)
+    }
+
+    fn var_log(&self) -> Precision {
+        let mean = self.mean_log();
+        self.resist.iter().fold(Precision::ZERO, |acc, &g| {
+            let d = g.ln() - mean;
+            acc + d * d
+        }) / Precision::new(self.resist.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::N_RES;
+    use approx::assert_relative_eq;
+
+    /// Image with a pixel per site and intensities growing across the drive.
+    fn gradient() -> Grayscale {
+        let pixels = (0..LX * LY)
+            .map(|k| (k % LY) as f64 / (LY - 1) as f64)
+            .collect();
+        Grayscale {
+            name: String::from("gradient"),
+            width: LY,
+            height: LX,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_mappings() {
+        let table = Mapping::table("t", "# u g\n0 1\n0.5 2\n1 6\n").unwrap();
+        for (u, g) in [(-1.0, 1.0), (0.25, 1.5), (0.75, 4.0), (2.0, 6.0)] {
+            assert_relative_eq!(table.apply(u), g, epsilon = 1e-12);
+        }
+
+        let log = Mapping::parse("log:1e-3:10").unwrap();
+        assert_relative_eq!(log.apply(0.5), 0.1, epsilon = 1e-12);
+        let power = Mapping::parse("power:2:3").unwrap();
+        assert_relative_eq!(power.apply(0.5), 0.25, epsilon = 1e-12);
+
+        for spec in [
+            "linear:0",
+            "log:0:1",
+            "power:1:0",
+            "cubic:0:1",
+            "linear:a:1",
+        ] {
+            assert!(Mapping::parse(spec).is_err(), "{spec:?}");
+        }
+        for text in ["0 1", "0 1\n0 2", "0 1\n0.5 3\n1 2", "0 -1\n1 1"] {
+            assert!(Mapping::table("t", text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_image_map() {
+        let map = ImageMap::with(gradient(), Mapping::parse("linear:1:3").unwrap());
+        assert_eq!(map.resist.len(), N_RES);
+
+        // Bonds join sites in series, and the resampled image is the image itself
+        let site = |col: usize| 1.0 + 2.0 * col as f64 / (LY - 1) as f64;
+        for (bond, g) in bonds().zip(map.resist.iter()) {
+            let expected = match bond {
+                Bond::Internal(a, b) => series(site(coords(a).1 + 1), site(coords(b).1 + 1)),
+                Bond::High(a) => series(site(0), site(coords(a).1 + 1)),
+                Bond::Low(a) => series(site(coords(a).1 + 1), site(LY - 1)),
+            };
+            assert_relative_eq!(g.into_f64(), expected, epsilon = 1e-12);
+        }
+
+        let mut rng = rand::rng();
+        let mut resist = vec![Precision::ZERO; N_RES];
+        map.fill(&mut rng, &mut resist);
+        assert!(resist == map.resist);
+        assert!(map.quantile(Precision::ZERO) == map.sorted[0]);
+        assert!(map.quantile(Precision::ONE) == map.sorted[N_RES - 1]);
+
+        assert!(map.cdf(Precision::ZERO) == Precision::ZERO);
+        assert!(map.cdf(map.sorted[N_RES - 1]) == Precision::ONE);
+        let logs: Vec<f64> = map.resist.iter().map(|g| g.into_f64().ln()).collect();
+        let mean_log = logs.iter().sum::<f64>() / N_RES as f64;
+        let var_log = logs.iter().map(|x| (x - mean_log).powi(2)).sum::<f64>() / N_RES as f64;
+        assert_relative_eq!(map.mean_log().into_f64(), mean_log, epsilon = 1e-12);
+        assert_relative_eq!(map.var_log().into_f64(), var_log, epsilon = 1e-12);
+    }
+}
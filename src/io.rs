@@ -1,7 +1,13 @@
-use crate::config::{Precision, L, N_UNK};
+use crate::config::{Precision, DIAGONALS, LATTICE, LX, LY, N_RES, PERIODIC};
+use crate::cubic::Mesh;
 use crate::dists::Distribution;
+use crate::dtn::ResponseMatrix;
+use crate::float::Float;
+use crate::graph::Network;
+use crate::hopping::{HoppingOptions, Site};
 use chrono::{Datelike, Timelike, Utc};
 use itertools::Itertools;
+use std::fmt;
 use std::fs::{metadata, File, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::path::Path;
@@ -10,6 +16,20 @@ use std::process::Command;
 // use regex::Regex;
 // use std::collections::HashMap;
 
+/// Lattice size used in file names, `L{LX}` for square samples and `L{LX}x{LY}` otherwise,
+/// followed by the tag of the lattice, by `d` when it has all the diagonals and by `p` when the
+/// transverse direction is periodic.
+pub fn size_tag() -> String {
+    let size = if LX == LY {
+        format!("L{}", LX)
+    } else {
+        format!("L{}x{}", LX, LY)
+    };
+    let diagonals = if DIAGONALS { "d" } else { "" };
+    let periodic = if PERIODIC { "p" } else { "" };
+    format!("{size}{}{diagonals}{periodic}", LATTICE.tag())
+}
+
 fn datetime() -> String {
     let now = Utc::now();
     format!(
@@ -24,9 +44,8 @@ fn datetime() -> String {
     )
 }
 
-#[allow(dead_code)]
-pub fn save_matrix(
-    matrix: &'static [[Precision; N_UNK + 1]; N_UNK],
+pub fn save_matrix<R: AsRef<[Precision]>>(
+    matrix: &[R],
     file_path: &Path,
     header: &Vec<String>,
 ) -> std::io::Result<()> {
@@ -40,6 +59,7 @@ pub fn save_matrix(
 
     for row in matrix.iter() {
         let row_string = row
+            .as_ref()
             .iter()
             .map(|&x| x.to_string())
             .collect::<Vec<String>>()
@@ -53,7 +73,7 @@ pub fn save_matrix(
 #[allow(dead_code)]
 pub fn save_array(
     file_path: &Path,
-    arr: &Vec<Precision>,
+    arr: &[Precision],
     header: &Vec<String>,
 ) -> std::io::Result<()> {
     let file = File::create(Path::new(file_path))?;
@@ -74,38 +94,159 @@ pub fn save_array(
     Ok(())
 }
 
-pub fn export_arrays<D: Distribution<Precision>>(
+/// Saves the potentials `x` to a `.x` file and the conductances to a `.r` file.
+///
+/// The `.r` file holds exactly `N_RES` values, one per bond in the order of `bonds()`. Files
+/// written while the lattice size was a single `L` end with `L` extra zeros, as that bond count
+/// had one column of horizontal bonds too many; the header now records `n_bonds`.
+pub fn export_arrays<D: fmt::Display>(
     dist: &D,
     outdir: &Path,
-    resist: &Vec<Precision>,
-    x: &Vec<Precision>,
+    resist: &[Precision],
+    x: &[Precision],
     err2: Precision,
     errinf: Precision,
     iter: usize,
 ) -> std::io::Result<()> {
     let header = vec![
         "Solution x of the system of equations".to_string(),
-        format!("L={L}"),
+        format!("Lx={LX}"),
+        format!("Ly={LY}"),
+        format!("periodic={PERIODIC}"),
+        format!("lattice={LATTICE}"),
+        format!("diagonals={DIAGONALS}"),
         format!("dist={}", dist),
         format!("error2={err2:.5e}"),
         format!("error_inf={errinf:.5e}"),
     ];
 
-    let x_path = outdir.join(format!("L{}_{}_{:04}.x", L, dist, iter));
-    save_array(&x_path, &x, &header)?;
+    let x_path = outdir.join(format!("{}_{}_{:04}.x", size_tag(), dist, iter));
+    save_array(&x_path, x, &header)?;
 
     let header = vec![
         "Resistances of the system of equations".to_string(),
-        format!("L={L}"),
+        format!("Lx={LX}"),
+        format!("Ly={LY}"),
+        format!("periodic={PERIODIC}"),
+        format!("lattice={LATTICE}"),
+        format!("n_bonds={N_RES}"),
         format!("dist={}", dist),
     ];
-    let resist_path = outdir.join(format!("L{}_{}_{:04}.r", L, dist, iter));
-    save_array(&resist_path, &resist, &header)?;
+    let resist_path = outdir.join(format!("{}_{}_{:04}.r", size_tag(), dist, iter));
+    save_array(&resist_path, resist, &header)?;
 
     Ok(())
 }
 
-pub fn export_surface(out_file: &Path, surface: &Vec<(usize, usize)>) -> std::io::Result<()> {
+/// Potentials and conductances of a sample of a graph network, the nodes listed in the header.
+pub fn export_graph_arrays<D: fmt::Display>(
+    dist: &D,
+    network: &Network,
+    outdir: &Path,
+    resist: &[Precision],
+    x: &[Precision],
+    iter: usize,
+) -> std::io::Result<()> {
+    let header = vec![
+        "Potentials of the nodes".to_string(),
+        format!("graph={}", network.name),
+        format!("dist={}", dist),
+        format!("nodes={}", network.ids.join(" ")),
+    ];
+    let x_path = outdir.join(format!("{}_{}_{:04}.x", network.name, dist, iter));
+    save_array(&x_path, x, &header)?;
+
+    let header = vec![
+        "Conductances of the bonds, in the order of the edge list".to_string(),
+        format!("graph={}", network.name),
+        format!("dist={}", dist),
+    ];
+    let resist_path = outdir.join(format!("{}_{}_{:04}.r", network.name, dist, iter));
+    save_array(&resist_path, resist, &header)
+}
+
+/// Sites of a sample of a hopping network, as rows of position and energy, and their potentials.
+pub fn export_hopping_arrays(
+    options: &HoppingOptions,
+    outdir: &Path,
+    sites: &[Site],
+    x: &[Precision],
+    iter: usize,
+) -> std::io::Result<()> {
+    let header = vec![
+        "Sites of the hopping network: x y energy".to_string(),
+        format!("network={}", options),
+    ];
+    let rows: Vec<[Precision; 3]> = sites
+        .iter()
+        .map(|s| [s.x, s.y, s.energy].map(Precision::new))
+        .collect();
+    let sites_path = outdir.join(format!("{}_{:04}.sites", options, iter));
+    save_matrix(&rows, &sites_path, &header)?;
+
+    let header = vec![
+        "Potentials of the sites".to_string(),
+        format!("network={}", options),
+    ];
+    let x_path = outdir.join(format!("{}_{:04}.x", options, iter));
+    save_array(&x_path, x, &header)
+}
+
+pub fn export_green<D: Distribution<Precision>>(
+    dist: &D,
+    outdir: &Path,
+    source: (usize, usize),
+    column: &[Precision],
+    resistances: &[Precision],
+    iter: usize,
+) -> std::io::Result<()> {
+    let (row, col) = source;
+    let header = vec![
+        "Green's function column of the grounded Laplacian".to_string(),
+        format!("Lx={LX}"),
+        format!("Ly={LY}"),
+        format!("periodic={PERIODIC}"),
+        format!("lattice={LATTICE}"),
+        format!("dist={}", dist),
+        format!("source={row} {col}"),
+    ];
+    let green_path = outdir.join(format!("green_{}_{}_{:04}.x", size_tag(), dist, iter));
+    save_array(&green_path, column, &header)?;
+
+    let line = resistances.iter().map(|r| r.to_string()).join(" ");
+    let resist_path = outdir.join(format!(
+        "resistance_{}_r{}c{}_{}.out",
+        size_tag(),
+        row,
+        col,
+        dist
+    ));
+    append_line(&resist_path, &line)
+}
+
+pub fn export_response<D: Distribution<Precision>>(
+    dist: &D,
+    outdir: &Path,
+    response: &ResponseMatrix,
+    iter: usize,
+) -> std::io::Result<()> {
+    let nodes = response.nodes.iter().map(|n| n.to_string()).join(" ");
+    let header = vec![
+        "Dirichlet-to-Neumann response matrix of the bus terminals".to_string(),
+        format!("Lx={LX}"),
+        format!("Ly={LY}"),
+        format!("periodic={PERIODIC}"),
+        format!("lattice={LATTICE}"),
+        format!("dist={}", dist),
+        format!("high_terminals={}", response.n_high),
+        format!("nodes={nodes}"),
+    ];
+    let dtn_path = outdir.join(format!("dtn_{}_{}_{:04}.out", size_tag(), dist, iter));
+    save_matrix(&response.lambda, &dtn_path, &header)
+}
+
+/// Appends `line` to `out_file`, writing the date first if the file is new.
+pub fn append_line(out_file: &Path, line: &str) -> std::io::Result<()> {
     let file_exists = metadata(out_file).is_ok();
     let file = OpenOptions::new()
         .append(true)
@@ -116,12 +257,82 @@ pub fn export_surface(out_file: &Path, surface: &Vec<(usize, usize)>) -> std::io
     if !file_exists {
         writeln!(writer, "#{}", datetime())?;
     }
+    writeln!(writer, "{}", line)?;
+
+    Ok(())
+}
+
+pub fn export_surface(out_file: &Path, surface: &[(usize, usize)]) -> std::io::Result<()> {
     let line = surface
         .iter()
         .map(|(r, c)| format!("{} {}", r, c))
         .join(" ");
 
-    writeln!(writer, "{}", line)?;
+    append_line(out_file, &line)
+}
+
+/// File formats of the isosurface meshes of cubic networks.
+#[derive(Clone, Copy, Debug)]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+}
+
+impl MeshFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+/// Writes `mesh` as an ASCII OBJ or PLY file, with `header` as comments.
+pub fn export_mesh(
+    mesh: &Mesh,
+    file_path: &Path,
+    format: MeshFormat,
+    header: &Vec<String>,
+) -> std::io::Result<()> {
+    let file = File::create(file_path)?;
+    let mut writer = BufWriter::new(file);
+
+    match format {
+        MeshFormat::Obj => {
+            writeln!(writer, "#{}", datetime())?;
+            for line in header {
+                writeln!(writer, "#{line}")?;
+            }
+            for [x, y, z] in mesh.vertices.iter() {
+                writeln!(writer, "v {x} {y} {z}")?;
+            }
+            // OBJ vertices are numbered from 1
+            for [a, b, c] in mesh.triangles.iter() {
+                writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+            }
+        }
+        MeshFormat::Ply => {
+            writeln!(writer, "ply")?;
+            writeln!(writer, "format ascii 1.0")?;
+            writeln!(writer, "comment {}", datetime())?;
+            for line in header {
+                writeln!(writer, "comment {line}")?;
+            }
+            writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+            writeln!(writer, "property double x")?;
+            writeln!(writer, "property double y")?;
+            writeln!(writer, "property double z")?;
+            writeln!(writer, "element face {}", mesh.triangles.len())?;
+            writeln!(writer, "property list uchar int vertex_indices")?;
+            writeln!(writer, "end_header")?;
+            for [x, y, z] in mesh.vertices.iter() {
+                writeln!(writer, "{x} {y} {z}")?;
+            }
+            for [a, b, c] in mesh.triangles.iter() {
+                writeln!(writer, "3 {a} {b} {c}")?;
+            }
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,286 @@
+//! Lattice geometries of the resistor network.
+//!
+//! Every lattice lives on the same grid of `LX` rows and `LY - 2` columns of nodes between the
+//! two buses, indexed by `fidx`, and the geometries differ only in the bonds joining the nodes.
+//! The triangular lattice adds the diagonal from `(i, j)` to `(i + 1, j + 1)` to every square
+//! plaquette, and the honeycomb lattice is drawn as a brick wall, keeping the vertical bond from
+//! `(i, j)` to `(i + 1, j)` only when `i + j` is even. Bus bonds are always horizontal, one per
+//! row of the first and last columns.
+//!
+//! With `diagonals` set in config.yaml, every plaquette also gets the diagonals it lacks, joining
+//! each node to its next-nearest neighbours. On the square lattice this gives the
+//! 8-neighbourhood.
+use crate::config::{DIAGONALS, LATTICE, LX, LY, PERIODIC};
+use std::fmt;
+
+// Periodic lattices need at least three rows for the bonds across the seam to be unambiguous,
+// and the honeycomb lattice needs an even number of rows to close without a defect.
+const _: () = assert!(!PERIODIC || LX > 2, "Periodic lattices need Lx > 2");
+const _: () = assert!(
+    !PERIODIC || !matches!(LATTICE, Lattice::Honeycomb) || LX.is_multiple_of(2),
+    "Periodic honeycomb lattices need an even Lx"
+);
+
+#[allow(dead_code)] // Only the lattice selected in config.yaml is constructed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lattice {
+    Square,
+    Triangular,
+    Honeycomb,
+}
+
+/// A resistor of the network, given by the nodes (or bus) it connects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bond {
+    Internal(usize, usize),
+    High(usize),
+    Low(usize),
+}
+
+/// Direction of a bond relative to the drive between the buses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    /// Along the drive: the bonds between columns in the same row, and those to the buses
+    Horizontal,
+    /// Across the drive, inside a column
+    Vertical,
+    /// Between columns and rows at once
+    Diagonal,
+}
+
+impl Bond {
+    pub fn orientation(self) -> Orientation {
+        match self {
+            Bond::Internal(a, b) if coords(a).1 == coords(b).1 => Orientation::Vertical,
+            Bond::Internal(a, b) if coords(a).0 != coords(b).0 => Orientation::Diagonal,
+            _ => Orientation::Horizontal,
+        }
+    }
+}
+
+#[inline]
+pub fn fidx(i: usize, j: usize) -> usize {
+    (i) + (j) * LX
+}
+
+/// Row and column of node `idx`.
+#[inline]
+pub fn coords(idx: usize) -> (usize, usize) {
+    (idx % LX, idx / LX)
+}
+
+/// Rows and columns travelled along the bond from `a` to `b`, going across the seam of a
+/// periodic lattice when that is the shorter way.
+pub fn displacement(a: usize, b: usize) -> (isize, isize) {
+    let (row_a, col_a) = coords(a);
+    let (row_b, col_b) = coords(b);
+    let mut d_row = row_b as isize - row_a as isize;
+    if PERIODIC && d_row.abs() > 1 {
+        d_row -= d_row.signum() * LX as isize;
+    }
+    (d_row, col_b as isize - col_a as isize)
+}
+
+const fn n_vertical(rows: usize) -> usize {
+    if PERIODIC {
+        rows
+    } else {
+        rows - 1
+    }
+}
+
+/// Bonds of the network of the configured lattice, in the same order as the values of `resist`.
+pub fn bonds() -> impl Iterator<Item = Bond> {
+    LATTICE.bonds()
+}
+
+impl Lattice {
+    /// Pairs of rows joined by the bonds inside column `col` of a lattice with `rows` rows.
+    pub fn column_bonds(self, rows: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        (0..n_vertical(rows))
+            .filter(move |&i| self != Lattice::Honeycomb || (i + col).is_multiple_of(2))
+            .map(move |i| (i, (i + 1) % rows))
+    }
+
+    /// Pairs of rows joined by the bonds from a column to the next one, the row in the first
+    /// column first.
+    pub fn link_bonds(self, rows: usize) -> impl Iterator<Item = (usize, usize)> {
+        let n_diagonal = if self.has_diagonals() {
+            n_vertical(rows)
+        } else {
+            0
+        };
+        let n_anti = if DIAGONALS { n_vertical(rows) } else { 0 };
+        let horizontal = (0..rows).map(|i| (i, i));
+        let diagonal = (0..n_diagonal).map(move |i| (i, (i + 1) % rows));
+        let anti = (0..n_anti).map(move |i| ((i + 1) % rows, i));
+        horizontal.chain(diagonal).chain(anti)
+    }
+
+    /// Whether the plaquettes have the diagonal from `(i, j)` to `(i + 1, j + 1)`.
+    const fn has_diagonals(self) -> bool {
+        DIAGONALS || matches!(self, Lattice::Triangular)
+    }
+
+    /// Number of bonds of a lattice with `rows` rows and `cols` columns between the buses.
+    pub const fn n_bonds(self, rows: usize, cols: usize) -> usize {
+        let vertical = match self {
+            Lattice::Honeycomb => {
+                let mut n = 0;
+                let mut col = 0;
+                while col < cols {
+                    let mut i = 0;
+                    while i < n_vertical(rows) {
+                        if (i + col).is_multiple_of(2) {
+                            n += 1;
+                        }
+                        i += 1;
+                    }
+                    col += 1;
+                }
+                n
+            }
+            _ => n_vertical(rows) * cols,
+        };
+        let mut links = rows;
+        if self.has_diagonals() {
+            links += n_vertical(rows);
+        }
+        if DIAGONALS {
+            links += n_vertical(rows);
+        }
+
+        vertical + links * (cols - 1) + 2 * rows
+    }
+
+    /// Bonds of the network, first those inside the columns, then those between consecutive
+    /// columns and finally those to the high and low buses.
+    pub fn bonds(self) -> impl Iterator<Item = Bond> {
+        self.grid_bonds(LX, LY - 2)
+    }
+
+    /// Bonds of a network of `rows` rows and `cols` columns of nodes, in the order of `bonds`,
+    /// with node `(i, j)` at index `i + j * rows`.
+    pub fn grid_bonds(self, rows: usize, cols: usize) -> impl Iterator<Item = Bond> {
+        let idx = move |i: usize, j: usize| i + j * rows;
+        let columns = (0..cols).flat_map(move |j| {
+            self.column_bonds(rows, j)
+                .map(move |(a, b)| Bond::Internal(idx(a, j), idx(b, j)))
+        });
+        let links = (0..cols - 1).flat_map(move |j| {
+            self.link_bonds(rows)
+                .map(move |(a, b)| Bond::Internal(idx(a, j), idx(b, j + 1)))
+        });
+
+        let high = (0..rows).map(move |i| Bond::High(idx(i, 0)));
+        let low = (0..rows).map(move |i| Bond::Low(idx(i, cols - 1)));
+
+        columns.chain(links).chain(high).chain(low)
+    }
+
+    /// Nodes joined to `(row, col)` by a bond: first those in the same column, then those in
+    /// the columns to the left and to the right.
+    pub fn neighbours(self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let up = self
+            .column_bonds(LX, col)
+            .filter(|&(_, b)| b == row)
+            .map(|(a, _)| (a, col));
+        let down = self
+            .column_bonds(LX, col)
+            .filter(|&(a, _)| a == row)
+            .map(|(_, b)| (b, col));
+        let left = self
+            .link_bonds(LX)
+            .filter(|&(_, b)| col > 0 && b == row)
+            .map(|(a, _)| (a, col - 1));
+        let right = self
+            .link_bonds(LX)
+            .filter(|&(a, _)| col < LY - 3 && a == row)
+            .map(|(_, b)| (b, col + 1));
+
+        up.chain(down).chain(left).chain(right).collect()
+    }
+
+    /// Suffix of the lattice in file names, empty for the square lattice.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Lattice::Square => "",
+            Lattice::Triangular => "t",
+            Lattice::Honeycomb => "h",
+        }
+    }
+}
+
+impl fmt::Display for Lattice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Lattice::Square => "square",
+            Lattice::Triangular => "triangular",
+            Lattice::Honeycomb => "honeycomb",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LATTICES: [Lattice; 3] = [Lattice::Square, Lattice::Triangular, Lattice::Honeycomb];
+
+    #[test]
+    fn test_n_bonds() {
+        for lattice in LATTICES {
+            assert_eq!(
+                lattice.bonds().count(),
+                lattice.n_bonds(LX, LY - 2),
+                "{lattice}"
+            );
+            for (rows, cols) in [(6, 11), (10, 3)] {
+                assert_eq!(
+                    lattice.grid_bonds(rows, cols).count(),
+                    lattice.n_bonds(rows, cols),
+                    "{lattice} {rows}x{cols}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_orientation() {
+        let n_vertical = bonds()
+            .filter(|bond| bond.orientation() == Orientation::Vertical)
+            .count();
+        let expected: usize = (0..LY - 2)
+            .map(|col| LATTICE.column_bonds(LX, col).count())
+            .sum();
+        assert_eq!(n_vertical, expected);
+
+        let n_diagonal = bonds()
+            .filter(|bond| bond.orientation() == Orientation::Diagonal)
+            .count();
+        let expected = LATTICE.link_bonds(LX).filter(|&(a, b)| a != b).count() * (LY - 3);
+        assert_eq!(n_diagonal, expected);
+    }
+
+    #[test]
+    fn test_coordination() {
+        // A node away from the edges and the seam
+        let (row, col) = (1, 2);
+        let coordination = if DIAGONALS { [8, 8, 7] } else { [4, 6, 3] };
+        for (lattice, z) in LATTICES.into_iter().zip(coordination) {
+            let neighs = lattice.neighbours(row, col);
+            assert_eq!(neighs.len(), z, "{lattice}");
+
+            let idx = fidx(row, col);
+            let n_bonds = lattice
+                .bonds()
+                .filter(|bond| match bond {
+                    Bond::Internal(a, b) => *a == idx || *b == idx,
+                    _ => false,
+                })
+                .count();
+            assert_eq!(n_bonds, z, "{lattice}");
+        }
+    }
+}
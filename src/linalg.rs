@@ -0,0 +1,121 @@
+//! Dense LU factorization with partial pivoting, for systems of any precision.
+use crate::config::N_THREADS;
+use crate::float::Float;
+use std::thread;
+
+/// Below this number of rows the elimination of a column is not split among threads.
+const MIN_PARALLEL_ROWS: usize = 64;
+
+fn eliminate_rows<T, R>(pivot: &[T], rows: &mut [R], j: usize, n: usize)
+where
+    T: Float,
+    R: AsMut<[T]>,
+{
+    for row in rows.iter_mut() {
+        let row = row.as_mut();
+        if row[j] == T::ZERO {
+            continue;
+        }
+        let factor = row[j] / pivot[j];
+        row[j] = factor;
+        for k in j + 1..n {
+            row[k] = row[k] - factor * pivot[k];
+        }
+    }
+}
+
+/// Factorizes the leading `n x n` block of `a` in place, storing the multipliers below the
+/// diagonal. Returns the row permutation applied while pivoting.
+pub fn lu_factorize<T, R>(a: &mut [R], n: usize) -> Result<Vec<usize>, String>
+where
+    T: Float,
+    R: AsRef<[T]> + AsMut<[T]> + Send,
+{
+    let mut perm: Vec<usize> = (0..n).collect();
+
+    for j in 0..n {
+        let pivot_row = (j..n)
+            .max_by(|&p, &q| {
+                let p = a[p].as_ref()[j].abs();
+                let q = a[q].as_ref()[j].abs();
+                p.partial_cmp(&q).unwrap()
+            })
+            .unwrap();
+
+        if a[pivot_row].as_ref()[j] == T::ZERO {
+            return Err(format!("No pivot found in column {} below the diagonal", j));
+        }
+
+        a.swap(j, pivot_row);
+        perm.swap(j, pivot_row);
+
+        let (top, bottom) = a.split_at_mut(j + 1);
+        let pivot = top[j].as_ref();
+        let rows = &mut bottom[..n - j - 1];
+
+        if rows.len() < MIN_PARALLEL_ROWS {
+            eliminate_rows(pivot, rows, j, n);
+        } else {
+            let chunk_size = rows.len().div_ceil(N_THREADS);
+            thread::scope(|s| {
+                for chunk in rows.chunks_mut(chunk_size) {
+                    s.spawn(move || eliminate_rows(pivot, chunk, j, n));
+                }
+            });
+        }
+    }
+
+    Ok(perm)
+}
+
+/// Solves `A x = b` in place, with `A` already factorized by `lu_factorize`.
+pub fn lu_solve<T, R>(a: &[R], perm: &[usize], b: &mut [T])
+where
+    T: Float,
+    R: AsRef<[T]>,
+{
+    let n = perm.len();
+    let mut x: Vec<T> = perm.iter().map(|&p| b[p]).collect();
+
+    for i in 0..n {
+        let row = a[i].as_ref();
+        let dot = (0..i).fold(T::ZERO, |acc, k| acc + row[k] * x[k]);
+        x[i] = x[i] - dot;
+    }
+
+    for i in (0..n).rev() {
+        let row = a[i].as_ref();
+        let dot = (i + 1..n).fold(T::ZERO, |acc, k| acc + row[k] * x[k]);
+        x[i] = (x[i] - dot) / row[i];
+    }
+
+    b[..n].copy_from_slice(&x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_lu_solve() {
+        let a = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 0.0],
+            vec![3.0, 0.0, 1.0],
+        ];
+        let expected = [1.0, -2.0, 3.0];
+        let mut b: Vec<f64> = a
+            .iter()
+            .map(|row| row.iter().zip(expected.iter()).map(|(a, x)| a * x).sum())
+            .collect();
+
+        let mut lu = a.clone();
+        let perm = lu_factorize(&mut lu, 3).unwrap();
+        lu_solve(&lu, &perm, &mut b);
+
+        for (x, e) in b.iter().zip(expected.iter()) {
+            assert_relative_eq!(x, e, epsilon = 1e-12);
+        }
+    }
+}
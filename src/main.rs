@@ -1,26 +1,84 @@
 #[macro_use]
 mod benchmarking;
 mod config;
+mod correlated;
+mod cubic;
+mod current;
 mod dists;
+mod dtn;
+mod electrodes;
+mod empirical;
+mod expr;
 mod float;
+mod graph;
+mod green;
+mod hopping;
+mod imagemap;
 mod io;
+mod lattice;
+mod linalg;
 mod math;
+mod percolation;
+mod shortcuts;
 mod solver;
+mod sparse;
+mod starmesh;
+mod tensor;
+mod transfer;
 
-use clap::Parser;
-use dists::{Distribution, InverseDist, LogNormalDist, ValidDists, WeibullDist};
-use solver::{compute_n_tries, ExportMode};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use config::{Precision, LX, LY};
+use correlated::{Correlated, Correlation};
+use cubic::CubicOptions;
+use current::{CurrentOptions, Drive, Sources};
+use dists::{Anisotropic, Diluted, Distribution, ValidDists};
+use electrodes::Electrodes;
+use empirical::Empirical;
+use expr::ExprDist;
+use graph::{GraphOptions, Network};
+use hopping::HoppingOptions;
+use imagemap::{Grayscale, ImageMap, Mapping};
+use io::MeshFormat;
+use shortcuts::{ShortcutOptions, Shortcuts};
+use solver::{compute_n_tries, ExportMode, RunOptions};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Distribution
-    #[arg(short, long)]
-    dist: String,
+    /// Distribution: inverse, weibull, lognormal, pareto, uniform, exponential, gamma,
+    /// loguniform, truncpowerlaw or binary
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["dist_h", "hopping", "image", "empirical", "dist_expr"],
+        conflicts_with = "dist_h"
+    )]
+    dist: Option<String>,
 
-    /// Parameter value
-    #[arg(short, long)]
-    param: String,
+    /// Parameter value, or the parameters of --dist-expr as name=value pairs separated by commas
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["dist_h", "hopping", "image", "empirical", "dist_expr"],
+        conflicts_with = "dist_h"
+    )]
+    param: Option<String>,
+
+    /// Distribution of the horizontal bonds as name:param or with named parameters, such as
+    /// weibull(k=3,lambda=2), instead of --dist and --param
+    #[arg(long)]
+    dist_h: Option<String>,
+
+    /// Distribution of the vertical bonds as a spec like --dist-h, or just the parameter for the
+    /// family of the horizontal one, if different from the horizontal one
+    #[arg(long)]
+    dist_v: Option<String>,
+
+    /// Distribution of the diagonal bonds as a spec like --dist-h, or just the parameter for the
+    /// family of the horizontal one, if different from the horizontal one
+    #[arg(long)]
+    dist_d: Option<String>,
 
     /// Export mode
     #[arg(short, long, default_value = "ExportIsoSurface")]
@@ -33,36 +91,418 @@ pub struct Args {
     /// Output directory
     #[arg(short, long, default_value_t = String::from("."))]
     outdir: String,
+
+    /// Cross-check every solution against the star-mesh reduction
+    #[arg(short, long)]
+    cross_check: bool,
+
+    /// Also compute the conductance tensor from horizontal and vertical drives
+    #[arg(short, long)]
+    tensor: bool,
+
+    /// Export the Green's function column of the node at ROW COL and its two-point resistances
+    #[arg(short, long, num_args = 2, value_names = ["ROW", "COL"])]
+    green: Option<Vec<usize>>,
+
+    /// Export the Dirichlet-to-Neumann response matrix of the bus terminals
+    #[arg(long)]
+    dtn: bool,
+
+    /// Electrode specification file, driving the samples through its electrodes instead of the
+    /// buses
+    #[arg(
+        long,
+        conflicts_with_all = ["cross_check", "tensor", "green", "dtn", "strip_width", "cubic"]
+    )]
+    electrodes: Option<String>,
+
+    /// Drive this total current from the high bus, left floating, to the grounded low bus
+    #[arg(
+        long,
+        conflicts_with_all = ["cross_check", "tensor", "green", "dtn", "electrodes", "strip_width", "cubic"]
+    )]
+    current: Option<f64>,
+
+    /// Current source specification file, driving the samples through its sources and grounded
+    /// node instead of the buses
+    #[arg(
+        long,
+        conflicts_with_all = ["cross_check", "tensor", "green", "dtn", "electrodes", "strip_width", "cubic", "current"]
+    )]
+    sources: Option<String>,
+
+    /// Edge-list file of a network to solve instead of the lattice
+    #[arg(
+        long,
+        conflicts_with_all = ["tensor", "green", "dtn", "electrodes", "current", "sources", "strip_width", "cubic", "dist_v", "dist_d", "corr_length"]
+    )]
+    graph: Option<String>,
+
+    /// Grayscale image (PGM or PNG) to read the conductances of the lattice from, instead of
+    /// drawing them from a distribution
+    #[arg(
+        long,
+        conflicts_with_all = ["dist", "dist_h", "dist_v", "dist_d", "dilute", "corr_length", "graph", "hopping", "strip_width", "cubic"]
+    )]
+    image: Option<String>,
+
+    /// Monotone mapping from the intensities of the image, scaled to [0, 1], to conductances:
+    /// linear:lo:hi, log:lo:hi, power:scale:exponent or table:FILE
+    #[arg(long, default_value = "linear:0:1", requires = "image")]
+    image_map: String,
+
+    /// File of measured conductances, a sample on every line or a bin `low high count`, to
+    /// draw the conductances from instead of a distribution
+    #[arg(long, conflicts_with_all = ["dist", "dist_h", "image", "hopping"])]
+    empirical: Option<String>,
+
+    /// Width in ln g of the lognormal kernel smoothing the measured samples, or auto for
+    /// Silverman's rule
+    #[arg(long, requires = "empirical")]
+    kernel_width: Option<String>,
+
+    /// Conductances as an expression of a uniform variate u in [0, 1), such as exp(a*u) with
+    /// --param a=30, built from + - * / ^, exp, ln, sqrt, erf and pow
+    #[arg(long, conflicts_with_all = ["dist", "dist_h", "image", "empirical", "hopping"])]
+    dist_expr: Option<String>,
+
+    /// Solve random networks of this many hopping sites with Miller–Abrahams conductances
+    /// instead of the lattice
+    #[arg(
+        long,
+        conflicts_with_all = ["tensor", "green", "dtn", "electrodes", "current", "sources", "strip_width", "cubic", "graph", "dist_h", "dist", "dilute", "corr_length"]
+    )]
+    hopping: Option<usize>,
+
+    /// Length along the drive and width of the rectangle of the hopping sites, by default a
+    /// square of unit density
+    #[arg(long, num_args = 2, value_names = ["LENGTH", "WIDTH"], requires = "hopping")]
+    hop_size: Option<Vec<f64>>,
+
+    /// Join the hopping sites closer than this distance, instead of by a Delaunay triangulation
+    #[arg(long, requires = "hopping")]
+    hop_cutoff: Option<f64>,
+
+    /// Localization length of the hopping sites
+    #[arg(long, default_value_t = 1.0)]
+    hop_xi: f64,
+
+    /// Temperature of the hopping network, in units of half the width of the energy band
+    #[arg(long, default_value_t = 1.0)]
+    hop_kt: f64,
+
+    /// Add long-range shortcuts to the lattice, with lengths distributed as r^(-ALPHA)
+    #[arg(
+        long,
+        value_name = "ALPHA",
+        conflicts_with_all = ["tensor", "green", "dtn", "electrodes", "current", "sources", "strip_width", "cubic", "graph", "hopping"]
+    )]
+    shortcuts: Option<f64>,
+
+    /// Probability that a node starts a shortcut
+    #[arg(long, default_value_t = 0.1, requires = "shortcuts")]
+    shortcut_density: f64,
+
+    /// Number of rows of the strip, for the transfer-matrix mode
+    #[arg(long, requires = "strip_length")]
+    strip_width: Option<usize>,
+
+    /// Number of columns of the strip, for the transfer-matrix mode
+    #[arg(long, requires = "strip_width")]
+    strip_length: Option<usize>,
+
+    /// Number of blocks of columns used to estimate the error in the transfer-matrix mode
+    #[arg(long, default_value_t = 32)]
+    strip_blocks: usize,
+
+    /// Solve a simple-cubic network with this many nodes per side instead of the 2D lattice
+    #[arg(long)]
+    cubic: Option<usize>,
+
+    /// Format of the isosurface meshes of the cubic mode (obj or ply)
+    #[arg(long, default_value = "obj")]
+    mesh_format: String,
+
+    /// Relative residual at which the conjugate gradient of the cubic, fixed-current, graph,
+    /// hopping and shortcut modes stops
+    #[arg(long, default_value_t = 1e-10)]
+    cg_tol: f64,
+
+    /// Fraction of bonds to dilute, removing them unless a replacement distribution is given
+    #[arg(long)]
+    dilute: Option<f64>,
+
+    /// Distribution of the diluted bonds
+    #[arg(long, requires_all = ["dilute", "dilute_param"])]
+    dilute_dist: Option<String>,
+
+    /// Parameter value of the distribution of the diluted bonds
+    #[arg(long, requires = "dilute_dist")]
+    dilute_param: Option<f64>,
+
+    /// Correlation length of the disorder, exponential unless an exponent is given
+    #[arg(long)]
+    corr_length: Option<f64>,
+
+    /// Exponent of the power-law decay of the correlations of the disorder
+    #[arg(long, requires = "corr_length")]
+    corr_exponent: Option<f64>,
 }
 
-fn main() {
-    let args = Args::parse();
-    let param = args.param.parse::<f64>().unwrap();
+/// Exits with a usage error for an invalid argument, as clap does for malformed ones.
+fn invalid_arg(e: String) -> ! {
+    Args::command().error(ErrorKind::ValueValidation, e).exit()
+}
+
+fn parse_dist(name: &str, param: f64) -> ValidDists<Precision> {
+    ValidDists::from_name(name, param).unwrap_or_else(|e| invalid_arg(e))
+}
+
+/// Parses a distribution given as `name:param` or as `name(key=value,...)`.
+fn parse_spec(spec: &str) -> ValidDists<Precision> {
+    ValidDists::from_spec(spec).unwrap_or_else(|e| invalid_arg(e))
+}
+
+/// Parses the distribution of a set of bonds, given as a spec or as just the parameter of the
+/// family of the horizontal bonds.
+fn parse_bond_spec(spec: &str, args: &Args) -> ValidDists<Precision> {
+    if let Ok(param) = spec.parse::<f64>() {
+        let family = match (&args.dist_h, &args.dist) {
+            (Some(dist_h), _) => dist_h.split([':', '(']).next().unwrap(),
+            (None, Some(name)) => name.as_str(),
+            _ => invalid_arg(format!(
+                "Distribution {} needs the family of --dist or --dist-h.",
+                spec
+            )),
+        };
+        return parse_dist(family, param);
+    }
+    parse_spec(spec)
+}
 
-    let dist = match args.dist.as_str() {
-        "inverse" => ValidDists::InverseDist(InverseDist::new(param)),
-        "weibull" => ValidDists::WeibullDist(WeibullDist::new(param)),
-        "lognormal" => ValidDists::LogNormalDist(LogNormalDist::new(param)),
-        _ => panic!("Distribution {} not supported.", args.dist),
+/// Runs `dist` for the horizontal bonds, with their own distributions for the vertical and
+/// diagonal ones if requested.
+fn dispatch<D: Distribution<Precision>>(dist: D, args: &Args, options: RunOptions) {
+    if args.dist_v.is_none() && args.dist_d.is_none() {
+        return dilute(dist, args, options);
+    }
+
+    let along = |spec: &Option<String>| spec.as_ref().map(|s| parse_bond_spec(s, args).boxed());
+    let (vertical, diagonal) = (along(&args.dist_v), along(&args.dist_d));
+    dilute(Anisotropic::with(dist, vertical, diagonal), args, options)
+}
+
+/// Runs `dist`, diluted if requested.
+fn dilute<D: Distribution<Precision>>(dist: D, args: &Args, options: RunOptions) {
+    match args.dilute {
+        Some(fraction) => {
+            let replacement = args
+                .dilute_dist
+                .as_ref()
+                .zip(args.dilute_param)
+                .map(|(name, param)| parse_dist(name, param).boxed());
+            let diluted =
+                Diluted::with(dist, fraction, replacement).unwrap_or_else(|e| invalid_arg(e));
+            correlate(diluted, args, options)
+        }
+        None => correlate(dist, args, options),
+    }
+}
+
+/// Runs `dist` on a correlated landscape if requested.
+fn correlate<D: Distribution<Precision>>(dist: D, args: &Args, options: RunOptions) {
+    let correlation = match (args.corr_length, args.corr_exponent) {
+        (Some(length), Some(exponent)) => Some(Correlation::PowerLaw { length, exponent }),
+        (Some(length), None) => Some(Correlation::Exponential { length }),
+        _ => None,
+    };
+
+    match correlation {
+        Some(correlation) => run(Correlated::with(dist, correlation), args, options),
+        None => run(dist, args, options),
+    }
+}
+
+fn run<D: Distribution<Precision>>(dist: D, args: &Args, options: RunOptions) {
+    let drive = match (args.current, &args.sources) {
+        (Some(current), _) => Some(Drive::Bus(current)),
+        (None, Some(path)) => {
+            Some(Drive::Sources(Sources::load(path).unwrap_or_else(|e| {
+                invalid_arg(format!("Invalid sources. {}", e))
+            })))
+        }
+        _ => None,
     };
+    if let Some(drive) = drive {
+        let current_options = CurrentOptions {
+            drive,
+            tolerance: args.cg_tol,
+            export_mode: options.export_mode,
+            isosurface_value: args.surfval,
+        };
+        current::compute_current(dist, &args.outdir, current_options);
+        return;
+    }
+
+    if let Some(path) = &args.graph {
+        let network =
+            Network::load(path).unwrap_or_else(|e| invalid_arg(format!("Invalid graph. {}", e)));
+        let graph_options = GraphOptions {
+            tolerance: args.cg_tol,
+            export_mode: options.export_mode,
+            isosurface_value: args.surfval,
+            cross_check: args.cross_check,
+        };
+        graph::compute_graph(dist, &network, &args.outdir, graph_options);
+        return;
+    }
+
+    if let Some(alpha) = args.shortcuts {
+        let shortcut_options = ShortcutOptions {
+            shortcuts: Shortcuts::with(alpha, args.shortcut_density)
+                .unwrap_or_else(|e| invalid_arg(e)),
+            tolerance: args.cg_tol,
+            export_mode: options.export_mode,
+            isosurface_value: args.surfval,
+            cross_check: args.cross_check,
+        };
+        shortcuts::compute_shortcuts(dist, &args.outdir, shortcut_options);
+        return;
+    }
+
+    if let Some(size) = args.cubic {
+        cubic::check_cubic(size).unwrap_or_else(|e| invalid_arg(e));
+        let mesh_format = match options.export_mode {
+            ExportMode::ExportIsoSurface | ExportMode::ExportArraysAndIsoSurface => {
+                match args.mesh_format.to_lowercase().as_str() {
+                    "obj" => Some(MeshFormat::Obj),
+                    "ply" => Some(MeshFormat::Ply),
+                    _ => invalid_arg(format!("Mesh format {} not supported.", args.mesh_format)),
+                }
+            }
+            _ => None,
+        };
+        let cubic_options = CubicOptions {
+            size,
+            isosurface_value: args.surfval,
+            tolerance: args.cg_tol,
+            mesh_format,
+        };
+        cubic::compute_cubic(dist, &args.outdir, cubic_options);
+        return;
+    }
+
+    match (args.strip_width, args.strip_length) {
+        (Some(width), Some(length)) => {
+            transfer::check_strip(width, length, args.strip_blocks)
+                .unwrap_or_else(|e| invalid_arg(e));
+            transfer::compute_strip(dist, width, length, args.strip_blocks, &args.outdir)
+        }
+        _ => compute_n_tries(dist, &args.outdir, options),
+    }
+}
 
+fn main() {
+    let args = Args::parse();
     let export_mode = match args.export.to_lowercase().as_str() {
         "noexport" => ExportMode::NoExport,
         "exportarrays" => ExportMode::ExportArrays,
         "exportisosurface" => ExportMode::ExportIsoSurface,
         "exportarraysandisosurface" => ExportMode::ExportArraysAndIsoSurface,
-        _ => panic!("Export mode {} not supported.", args.export),
+        _ => invalid_arg(format!("Export mode {} not supported.", args.export)),
     };
 
-    match dist {
-        ValidDists::InverseDist(inner) => {
-            compute_n_tries(inner, &args.outdir, export_mode, args.surfval)
-        }
-        ValidDists::WeibullDist(inner) => {
-            compute_n_tries(inner, &args.outdir, export_mode, args.surfval)
-        }
-        ValidDists::LogNormalDist(inner) => {
-            compute_n_tries(inner, &args.outdir, export_mode, args.surfval)
+    if let Some(n_sites) = args.hopping {
+        let size = match &args.hop_size {
+            Some(size) => (size[0], size[1]),
+            None => ((n_sites as f64).sqrt(), (n_sites as f64).sqrt()),
+        };
+        let hopping_options = HoppingOptions {
+            n_sites,
+            size,
+            cutoff: args.hop_cutoff,
+            xi: args.hop_xi,
+            temperature: args.hop_kt,
+            tolerance: args.cg_tol,
+            export_mode,
+            isosurface_value: args.surfval,
+            cross_check: args.cross_check,
+        };
+        hopping_options.check().unwrap_or_else(|e| invalid_arg(e));
+        hopping::compute_hopping(&args.outdir, hopping_options);
+        return;
+    }
+
+    if let Some(g) = &args.green {
+        if g[0] >= LX || g[1] >= LY - 2 {
+            invalid_arg(format!("Node {} {} is outside the lattice.", g[0], g[1]));
         }
     }
+
+    if args.corr_length.is_some() && (args.cubic.is_some() || args.strip_width.is_some()) {
+        invalid_arg(String::from(
+            "Correlated disorder is only supported on the 2D lattice.",
+        ));
+    }
+
+    let image = args.image.as_ref().map(|path| {
+        Grayscale::load(path).unwrap_or_else(|e| invalid_arg(format!("Invalid image. {}", e)))
+    });
+
+    let options = RunOptions {
+        export_mode,
+        isosurface_value: args.surfval,
+        cross_check: args.cross_check,
+        tensor: args.tensor,
+        green_source: args.green.as_ref().map(|g| (g[0], g[1])),
+        dtn: args.dtn,
+        electrodes: args.electrodes.as_ref().map(|path| {
+            Electrodes::load(path)
+                .unwrap_or_else(|e| invalid_arg(format!("Invalid electrodes. {}", e)))
+        }),
+        overlay: image.clone(),
+    };
+
+    if let Some(image) = image {
+        let mapping = Mapping::parse(&args.image_map)
+            .unwrap_or_else(|e| invalid_arg(format!("Invalid image mapping. {}", e)));
+        run(ImageMap::with(image, mapping), &args, options);
+        return;
+    }
+
+    if let Some(path) = &args.empirical {
+        let dist = Empirical::load(path, args.kernel_width.as_deref())
+            .unwrap_or_else(|e| invalid_arg(format!("Invalid empirical distribution. {}", e)));
+        dispatch(dist, &args, options);
+        return;
+    }
+
+    if let Some(text) = &args.dist_expr {
+        let dist = ExprDist::parse(text, args.param.as_deref().unwrap_or(""))
+            .unwrap_or_else(|e| invalid_arg(format!("Invalid distribution expression. {}", e)));
+        dispatch(dist, &args, options);
+        return;
+    }
+
+    let dist = match (&args.dist_h, &args.dist, &args.param) {
+        (Some(spec), _, _) => parse_spec(spec),
+        (None, Some(name), Some(param)) => match param.parse::<f64>() {
+            Ok(param) => parse_dist(name, param),
+            Err(e) => invalid_arg(format!("Invalid parameter {}: {}", param, e)),
+        },
+        _ => unreachable!(),
+    };
+
+    match dist {
+        ValidDists::InverseDist(inner) => dispatch(inner, &args, options),
+        ValidDists::WeibullDist(inner) => dispatch(inner, &args, options),
+        ValidDists::LogNormalDist(inner) => dispatch(inner, &args, options),
+        ValidDists::ParetoDist(inner) => dispatch(inner, &args, options),
+        ValidDists::UniformDist(inner) => dispatch(inner, &args, options),
+        ValidDists::ExponentialDist(inner) => dispatch(inner, &args, options),
+        ValidDists::GammaDist(inner) => dispatch(inner, &args, options),
+        ValidDists::LogUniformDist(inner) => dispatch(inner, &args, options),
+        ValidDists::TruncatedPowerLawDist(inner) => dispatch(inner, &args, options),
+        ValidDists::BinaryDist(inner) => dispatch(inner, &args, options),
+    }
 }
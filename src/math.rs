@@ -13,11 +13,25 @@ pub fn powi256(x: f256, n: u64) -> f256 {
     (0..n).fold(f256::ONE, |a, _b| a * x)
 }
 
+/// Exponential by its Taylor series, on the argument halved below 1/2 and squared back, since
+/// the series alone loses all precision for large arguments.
 pub fn exp256(x: f256) -> f256 {
-    f256::ONE
+    if !x.is_finite() {
+        return if x > f256::ZERO { x } else { f256::ZERO };
+    }
+    let half = f256::from(0.5);
+    let mut halvings = 0;
+    let mut r = x;
+    while r.abs() > half {
+        r /= f256::TWO;
+        halvings += 1;
+    }
+
+    let series = f256::ONE
         + (1..=EXP_ITERS)
-            .map(|n| powi256(x, n) / factorial256(n))
-            .fold(f256::ZERO, |a, b| a + b)
+            .map(|n| powi256(r, n) / factorial256(n))
+            .fold(f256::ZERO, |a, b| a + b);
+    (0..halvings).fold(series, |y, _| y * y)
 }
 
 pub fn powf256(b: f256, x: f256) -> f256 {
@@ -31,6 +45,7 @@ pub fn intof64(x: f256) -> f64 {
 
 const G: f64 = 7.0;
 const N: usize = 9;
+#[allow(clippy::excessive_precision)]
 const P: [f64; N] = [
     0.99999999999980993,
     676.5203681218851,
@@ -49,8 +64,8 @@ pub fn gammaf64(z: f64) -> f64 {
     } else {
         let z = z - 1.0;
         let mut x = P[0];
-        for i in 1..N {
-            x += P[i] / (z + i as f64);
+        for (i, p) in P.iter().enumerate().skip(1) {
+            x += p / (z + i as f64);
         }
         let t = z + G + 0.5;
         (2.0 * PI).sqrt() * t.powf(z + 0.5) * (-t).exp() * x
@@ -61,6 +76,7 @@ pub fn gammaf32(z: f32) -> f32 {
     gammaf64(z.into()) as f32
 }
 
+#[allow(clippy::excessive_precision)]
 pub fn gammaf256(z: f256) -> f256 {
     let pi256: f256 = f256::from(PI);
     let half: f256 = f256::from(0.5);
@@ -82,8 +98,8 @@ pub fn gammaf256(z: f256) -> f256 {
     } else {
         let z = z - f256::ONE;
         let mut x = p[0];
-        for i in 1..N {
-            x += p[i] / (z + f256::from(i as f64));
+        for (i, p) in p.iter().enumerate().skip(1) {
+            x += p / (z + f256::from(i as f64));
         }
         let t = z + g + half;
         (f256::TWO * pi256).sqrt() * powf256(t, z + half) * exp256(-t) * x
@@ -155,6 +171,258 @@ pub fn erff256(x: f256) -> f256 {
     sign * (f256::ONE - poly * temp)
 }
 
+/// Cumulative distribution function of the standard normal distribution, from the incomplete
+/// gamma function of shape 1/2, to the precision of `T`.
+pub fn normal_cdf<T: Float>(x: T) -> T {
+    let half = T::ONE / T::TWO;
+    let (p, q) = incomplete_gamma(half, x * x / T::TWO, T::PI.ln() / T::TWO);
+    if x < T::ZERO {
+        q / T::TWO
+    } else {
+        (T::ONE + p) / T::TWO
+    }
+}
+
+/// Quantile function of the standard normal distribution, by Acklam's rational approximation
+/// refined with Halley's method to the precision of `T`.
+pub fn normal_quantile<T: Float>(u: T) -> T {
+    const MAX_ITERS: usize = 10;
+
+    let z = acklam(u.into_f64());
+    if !z.is_finite() {
+        return T::new(z);
+    }
+    let norm = (T::TWO * T::PI).sqrt();
+    let mut z = T::new(z);
+    for _ in 0..MAX_ITERS {
+        let t = (normal_cdf(z) - u) * norm * (z * z / T::TWO).exp();
+        z = z - t / (T::ONE + z * t / T::TWO);
+        if t.abs() <= T::EPSILON * (T::ONE + z.abs()) {
+            break;
+        }
+    }
+    z
+}
+
+/// Acklam's rational approximation of the standard normal quantile (relative error below
+/// 1.2e-9).
+#[allow(clippy::excessive_precision)]
+fn acklam(u: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.383577518672690e2,
+        -3.066479806614716e1,
+        2.506628277459239e0,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838e0,
+        -2.549732539343734e0,
+        4.374664141464968e0,
+        2.938163982698783e0,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996e0,
+        3.754408661907416e0,
+    ];
+    const U_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if u < U_LOW {
+        tail((-2.0 * u.ln()).sqrt())
+    } else if u > 1.0 - U_LOW {
+        -tail((-2.0 * (1.0 - u).ln()).sqrt())
+    } else {
+        let q = u - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Bernoulli numbers B_2 to B_34 as numerators and denominators, for the asymptotic series of
+/// `ln Γ` and its derivatives.
+const BERNOULLI: [(f64, f64); 17] = [
+    (1.0, 6.0),
+    (-1.0, 30.0),
+    (1.0, 42.0),
+    (-1.0, 30.0),
+    (5.0, 66.0),
+    (-691.0, 2730.0),
+    (7.0, 6.0),
+    (-3617.0, 510.0),
+    (43867.0, 798.0),
+    (-174611.0, 330.0),
+    (854513.0, 138.0),
+    (-236364091.0, 2730.0),
+    (8553103.0, 6.0),
+    (-23749461029.0, 870.0),
+    (8615841276005.0, 14322.0),
+    (-7709321041217.0, 510.0),
+    (2577687858367.0, 6.0),
+];
+
+/// Argument above which the asymptotic series are used, the truncation error of the 17 terms
+/// being then below 1e-60.
+const ASYMPTOTIC_MIN: f64 = 100.0;
+
+/// Sum over k of `B_2k * coefficient(k) * first * ratio^(k - 1)`.
+fn bernoulli_sum<T: Float, F: Fn(f64) -> f64>(first: T, ratio: T, coefficient: F) -> T {
+    let mut power = first;
+    let mut sum = T::ZERO;
+    for (k, &(num, den)) in (1..).zip(BERNOULLI.iter()) {
+        sum = sum + T::new(num) / T::new(den * coefficient(k as f64)) * power;
+        power = power * ratio;
+    }
+    sum
+}
+
+/// Logarithm of the gamma function for `x > 0`, by the recurrence up to `ASYMPTOTIC_MIN` and
+/// Stirling's series beyond.
+pub fn ln_gamma<T: Float>(x: T) -> T {
+    let (mut x, mut product) = (x, T::ONE);
+    while x.lt(T::new(ASYMPTOTIC_MIN)) {
+        product = product * x;
+        x = x + T::ONE;
+    }
+    let series = bernoulli_sum(T::ONE / x, T::ONE / (x * x), |k| 2.0 * k * (2.0 * k - 1.0));
+    (x - T::ONE / T::TWO) * x.ln() - x + (T::TWO * T::PI).ln() / T::TWO + series - product.ln()
+}
+
+/// Regularized lower and upper incomplete gamma functions P(a, x) and Q(a, x), given `ln Γ(a)`,
+/// by the series of P below `a + 1` and by the continued fraction of Q above (Numerical Recipes,
+/// section 6.2). The other one is its complement.
+fn incomplete_gamma<T: Float>(a: T, x: T, ln_gamma_a: T) -> (T, T) {
+    const MAX_ITERS: usize = 1000;
+    let tiny = T::new(1e-300);
+
+    if x <= T::ZERO {
+        return (T::ZERO, T::ONE);
+    }
+    let prefactor = (a * x.ln() - x - ln_gamma_a).exp();
+
+    if x.lt(a + T::ONE) {
+        let (mut term, mut sum) = (T::ONE / a, T::ONE / a);
+        for n in 1..MAX_ITERS {
+            term = term * x / (a + T::new(n as f64));
+            sum = sum + term;
+            if term.abs().lt(sum.abs() * T::EPSILON) {
+                break;
+            }
+        }
+        let p = sum * prefactor;
+        (p, T::ONE - p)
+    } else {
+        // Modified Lentz's method
+        let mut b = x + T::ONE - a;
+        let mut c = T::ONE / tiny;
+        let mut d = T::ONE / b;
+        let mut h = d;
+        for n in 1..MAX_ITERS {
+            let n = T::new(n as f64);
+            let an = -n * (n - a);
+            b = b + T::TWO;
+            d = an * d + b;
+            if d.abs().lt(tiny) {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs().lt(tiny) {
+                c = tiny;
+            }
+            d = T::ONE / d;
+            h = h * d * c;
+            if (d * c - T::ONE).abs().lt(T::EPSILON) {
+                break;
+            }
+        }
+        let q = prefactor * h;
+        (T::ONE - q, q)
+    }
+}
+
+/// Regularized lower incomplete gamma function P(a, x), to the precision of `T`.
+pub fn regularized_gamma<T: Float>(a: T, x: T) -> T {
+    incomplete_gamma(a, x, ln_gamma(a)).0
+}
+
+/// Quantile function of the gamma distribution of shape `a` and unit scale, by Newton's method
+/// kept inside a bracket of the root by bisection.
+pub fn gamma_quantile<T: Float>(a: T, u: T) -> T {
+    const MAX_ITERS: usize = 200;
+
+    let ln_gamma_a = ln_gamma(a);
+    let cdf = |x: T| incomplete_gamma(a, x, ln_gamma_a).0;
+    let (mut lo, mut hi) = (T::ZERO, if a.lt(T::ONE) { T::ONE } else { a });
+    while cdf(hi).lt(u) {
+        lo = hi;
+        hi = hi * T::TWO;
+    }
+
+    let tolerance = T::new(16.0) * T::EPSILON;
+    let mut x = (lo + hi) / T::TWO;
+    for _ in 0..MAX_ITERS {
+        let f = cdf(x) - u;
+        if f < T::ZERO {
+            lo = x;
+        } else {
+            hi = x;
+        }
+        let step = f / ((a - T::ONE) * x.ln() - x - ln_gamma_a).exp();
+        let next = x - step;
+        x = if next.lt(lo) || hi.lt(next) {
+            (lo + hi) / T::TWO
+        } else {
+            next
+        };
+        if step.abs().lt(tolerance * x) || (hi - lo).lt(tolerance * x) {
+            break;
+        }
+    }
+    x
+}
+
+/// Digamma function, the derivative of `ln Γ`, by its recurrence up to `ASYMPTOTIC_MIN` and its
+/// asymptotic series beyond.
+pub fn digamma<T: Float>(x: T) -> T {
+    let (mut x, mut shift) = (x, T::ZERO);
+    while x.lt(T::new(ASYMPTOTIC_MIN)) {
+        shift = shift - T::ONE / x;
+        x = x + T::ONE;
+    }
+    let r = T::ONE / (x * x);
+    let series = bernoulli_sum(r, r, |k| 2.0 * k);
+    shift + x.ln() - T::ONE / (T::TWO * x) - series
+}
+
+/// Trigamma function, the derivative of the digamma function, computed the same way.
+pub fn trigamma<T: Float>(x: T) -> T {
+    let (mut x, mut shift) = (x, T::ZERO);
+    while x.lt(T::new(ASYMPTOTIC_MIN)) {
+        shift = shift + T::ONE / (x * x);
+        x = x + T::ONE;
+    }
+    let r = T::ONE / (x * x);
+    let series = bernoulli_sum(r / x, r, |_| 1.0);
+    shift + T::ONE / x + T::ONE / (T::TWO * x * x) + series
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,6 +430,9 @@ mod tests {
 
     const EPS: f64 = 1e-5;
 
+    /// Euler–Mascheroni constant, `-digamma(1)`.
+    const EULER_GAMMA: f64 = 0.5772156649015329;
+
     #[test]
     fn test_exp() {
         let x: f64 = 3.0;
@@ -183,6 +454,11 @@ mod tests {
         let actual = intof64(exp256(f256::from(x)));
         let expected = x.exp();
         assert_relative_eq!(actual, expected, epsilon = EPS);
+
+        for x in [-40.0, -13.8, 25.0, 300.0] {
+            let actual = intof64(exp256(f256::from(x)));
+            assert_relative_eq!(actual / f64::exp(x), 1.0, epsilon = 1e-12);
+        }
     }
 
     #[test]
@@ -201,4 +477,95 @@ mod tests {
         assert_relative_eq!(actualf64, expected, epsilon = EPS);
         assert_relative_eq!(actualf256, expected, epsilon = EPS);
     }
+
+    #[test]
+    fn test_normal_quantile() {
+        for u in [1e-6, 0.01, 0.1, 0.3, 0.5, 0.8, 0.99, 1.0 - 1e-6] {
+            assert_relative_eq!(normal_cdf(normal_quantile(u)), u, epsilon = EPS);
+        }
+        assert_relative_eq!(normal_quantile(0.975), 1.959963985, epsilon = EPS);
+    }
+
+    #[test]
+    fn test_regularized_gamma() {
+        // Exponential and chi-squared with one degree of freedom
+        for x in [0.01, 0.5, 1.0, 3.0, 20.0] {
+            assert_relative_eq!(regularized_gamma(1.0, x), 1.0 - (-x).exp(), epsilon = 1e-12);
+        }
+        for x in [0.01, 0.5, 1.0, 3.0] {
+            assert_relative_eq!(
+                regularized_gamma(0.5, x),
+                erf_exact(x.sqrt()),
+                epsilon = 1e-12
+            );
+        }
+        for a in [0.3, 1.0, 2.5, 10.0] {
+            for u in [1e-6, 0.01, 0.3, 0.5, 0.9, 1.0 - 1e-6] {
+                assert_relative_eq!(
+                    regularized_gamma(a, gamma_quantile(a, u)),
+                    u,
+                    epsilon = 1e-10
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_polygamma() {
+        assert_relative_eq!(digamma(1.0), -EULER_GAMMA, epsilon = 1e-14);
+        assert_relative_eq!(
+            digamma(0.5),
+            -EULER_GAMMA - 2.0 * 2f64.ln(),
+            epsilon = 1e-14
+        );
+        assert_relative_eq!(trigamma(1.0), PI * PI / 6.0, epsilon = 1e-14);
+        assert_relative_eq!(trigamma(0.5), PI * PI / 2.0, epsilon = 1e-14);
+        for x in [0.1, 2.5, 9.5, 30.0] {
+            assert_relative_eq!(digamma(x + 1.0), digamma(x) + 1.0 / x, epsilon = 1e-13);
+            assert_relative_eq!(
+                trigamma(x + 1.0),
+                trigamma(x) - 1.0 / (x * x),
+                epsilon = 1e-13
+            );
+        }
+    }
+
+    #[test]
+    fn test_f256_functions() {
+        let tolerance = f256::from(1e-40);
+        let euler_gamma: f256 =
+            "0.57721566490153286060651209008240243104215933593992359880576723488486772677766467"
+                .parse()
+                .unwrap();
+        assert!((digamma(f256::ONE) + euler_gamma).abs() < tolerance);
+        let pi = f256::PI;
+        assert!((trigamma(f256::ONE) - pi * pi / f256::from(6.0)).abs() < tolerance);
+        assert!((ln_gamma(f256::from(0.5)) - pi.ln() / f256::TWO).abs() < tolerance);
+        assert!((ln_gamma(f256::from(6.0)) - f256::from(120.0).ln()).abs() < tolerance);
+
+        for x in [0.01, 1.0, 3.0, 20.0] {
+            let x = f256::from(x);
+            let expected = f256::ONE - exp256(-x);
+            assert!((regularized_gamma(f256::ONE, x) - expected).abs() < tolerance);
+        }
+        for u in [1e-6, 0.3, 0.5, 0.99] {
+            let u = f256::from(u);
+            let z = normal_quantile(u);
+            assert!((normal_cdf(z) - u).abs() < tolerance);
+            let x = gamma_quantile(f256::from(2.5), u);
+            assert!((regularized_gamma(f256::from(2.5), x) - u).abs() < tolerance);
+        }
+    }
+
+    /// Error function to double precision for small arguments, by its Taylor series (the
+    /// rational approximation of `erff64` is only good to 1e-7).
+    fn erf_exact(x: f64) -> f64 {
+        let mut term = x;
+        let mut sum = x;
+        for n in 1..200 {
+            term *= -x * x / n as f64;
+            sum += term / (2 * n + 1) as f64;
+        }
+        2.0 / PI.sqrt() * sum
+    }
 }
@@ -0,0 +1,74 @@
+//! Connectivity of diluted networks.
+//!
+//! Once bonds of zero conductance are removed, clusters of nodes joined to neither bus have a
+//! singular Laplacian and no defined potential. They are found here so that the solvers can
+//! drop them from the system and the isosurface search can skip them.
+use crate::float::Float;
+use crate::lattice::Bond;
+use std::collections::VecDeque;
+
+/// Marks the nodes among `n` joined by bonds of nonzero conductance to a bus or to a node for
+/// which `pinned` holds, such as the nodes held at a fixed potential.
+pub fn connected<T, B>(
+    n: usize,
+    bonds: B,
+    resist: &[T],
+    pinned: impl Fn(usize) -> bool,
+) -> Vec<bool>
+where
+    T: Float,
+    B: Iterator<Item = Bond>,
+{
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut reached: Vec<bool> = (0..n).map(&pinned).collect();
+
+    for (bond, &r) in bonds.zip(resist) {
+        if r == T::ZERO {
+            continue;
+        }
+        match bond {
+            Bond::Internal(a, b) => {
+                adj[a].push(b);
+                adj[b].push(a);
+            }
+            Bond::High(a) | Bond::Low(a) => reached[a] = true,
+        }
+    }
+
+    let mut queue: VecDeque<usize> = (0..n).filter(|&i| reached[i]).collect();
+    while let Some(a) = queue.pop_front() {
+        for &b in adj[a].iter() {
+            if !reached[b] {
+                reached[b] = true;
+                queue.push_back(b);
+            }
+        }
+    }
+
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolated_cluster() {
+        // High - 0 - 1 - Low, and the pair 2 - 3 on its own next to the isolated node 4
+        let bonds = [
+            Bond::High(0),
+            Bond::Internal(0, 1),
+            Bond::Low(1),
+            Bond::Internal(1, 2),
+            Bond::Internal(2, 3),
+            Bond::Internal(3, 4),
+        ];
+        let resist = [1.0, 1.0, 1.0, 0.0, 1.0, 0.0];
+
+        let active = connected(5, bonds.into_iter(), &resist, |_| false);
+        assert_eq!(active, [true, true, false, false, false]);
+
+        let active = connected(5, bonds.into_iter(), &resist, |i| i == 3);
+        assert_eq!(active, [true, true, true, true, false]);
+    }
+}
@@ -0,0 +1,272 @@
+//! Long-range shortcut bonds.
+//!
+//! On top of the bonds of the lattice, every node starts a shortcut with a given probability,
+//! to a partner drawn among the nodes farther away than the diagonals with probability decaying
+//! as `r^(-alpha)`. Distances are measured in lattice spacings, across the rows around the
+//! cylinder when the lattice is periodic. Shortcuts take their conductances from the same
+//! distribution as the other bonds.
+//!
+//! The shortcuts break the band structure of the system, which is assembled as a sparse matrix
+//! and solved by conjugate gradients as the networks of edge lists are. The isosurface is still
+//! the one of the lattice, found across its nearest-neighbour bonds.
+use crate::config::{Precision, LX, N_RES, N_TRIES, N_UNK, PERIODIC};
+use crate::dists::Distribution;
+use crate::float::Float;
+use crate::graph;
+use crate::io;
+use crate::lattice::{bonds, coords, Bond};
+use crate::solver::{compute_isosurface, ExportMode};
+use crate::starmesh;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::fmt;
+use std::path::Path;
+
+/// Shortcuts join nodes farther apart than this, past the nearest neighbours and the diagonals.
+const MIN_DISTANCE: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Shortcuts {
+    /// Exponent of the decay of the probability with the length of a shortcut
+    pub alpha: f64,
+    /// Probability that a node starts a shortcut
+    pub density: f64,
+}
+
+impl fmt::Display for Shortcuts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sc(a={:.3},p={:.3})", self.alpha, self.density)
+    }
+}
+
+/// Distance between two nodes, in lattice spacings.
+fn distance(a: usize, b: usize) -> f64 {
+    let ((ra, ca), (rb, cb)) = (coords(a), coords(b));
+    let dr = ra.abs_diff(rb);
+    let dr = if PERIODIC { dr.min(LX - dr) } else { dr };
+    (dr as f64).hypot(ca.abs_diff(cb) as f64)
+}
+
+impl Shortcuts {
+    pub fn with(alpha: f64, density: f64) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&density) {
+            return Err(format!("Shortcut density {} is not in [0, 1].", density));
+        }
+        if !alpha.is_finite() {
+            return Err(format!("Shortcut exponent {} is not finite.", alpha));
+        }
+        Ok(Shortcuts { alpha, density })
+    }
+
+    /// Unnormalized probabilities of the partners of a shortcut from `source`.
+    fn weights(&self, source: usize) -> Vec<f64> {
+        (0..N_UNK)
+            .map(|b| {
+                let r = distance(source, b);
+                if r > MIN_DISTANCE {
+                    r.powf(-self.alpha)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Nodes joined by the shortcuts of a sample.
+    pub fn draw(&self, rng: &mut ThreadRng) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+
+        for a in 0..N_UNK {
+            if rng.random::<f64>() >= self.density {
+                continue;
+            }
+            let weights = self.weights(a);
+            let total: f64 = weights.iter().sum();
+            let mut target = total * rng.random::<f64>();
+            let b = weights
+                .iter()
+                .position(|&w| {
+                    target -= w;
+                    target < 0.0
+                })
+                .unwrap_or_else(|| weights.iter().rposition(|&w| w > 0.0).unwrap());
+            pairs.push((a, b));
+        }
+
+        pairs
+    }
+}
+
+/// Settings of a run of `compute_shortcuts`.
+pub struct ShortcutOptions {
+    pub shortcuts: Shortcuts,
+    /// Relative residual at which the conjugate gradient stops
+    pub tolerance: f64,
+    pub export_mode: ExportMode,
+    pub isosurface_value: f64,
+    /// Cross-check every solution against the star-mesh reduction
+    pub cross_check: bool,
+}
+
+/// Bonds of the lattice followed by the shortcuts, with their conductances.
+fn sample_network<D: Distribution<Precision>>(
+    dist: &D,
+    shortcuts: &Shortcuts,
+    rng: &mut ThreadRng,
+) -> (Vec<Bond>, Vec<Precision>) {
+    let mut resist: Vec<Precision> = vec![Precision::ZERO; N_RES];
+    dist.fill(rng, &mut resist);
+
+    let pairs = shortcuts.draw(rng);
+    let mut all_bonds: Vec<Bond> = bonds().collect();
+    all_bonds.extend(pairs.iter().map(|&(a, b)| Bond::Internal(a, b)));
+    resist.extend(pairs.iter().map(|_| dist.sample(rng)));
+
+    (all_bonds, resist)
+}
+
+pub fn compute_shortcuts<D, P>(dist: D, outdir: P, options: ShortcutOptions)
+where
+    D: Distribution<Precision>,
+    P: AsRef<Path>,
+{
+    let outdir: &Path = outdir.as_ref();
+    let mut rng = rand::rng();
+    let tol = Precision::new(options.tolerance);
+    let value = Precision::new(options.isosurface_value);
+    let label = format!("{}_{}", dist, options.shortcuts);
+
+    for iter in 0..N_TRIES {
+        let (all_bonds, resist) = sample_network(&dist, &options.shortcuts, &mut rng);
+        let n_shortcuts = all_bonds.len() - N_RES;
+        let (duration, result) =
+            timeit!(graph::solve_sample(N_UNK, &all_bonds, &resist, tol, value));
+
+        let solution = match result {
+            Ok(solution) => solution,
+            Err(e) => {
+                println!(
+                    "{}/{}  {:>5}  dist={}  FAIL - {}",
+                    iter + 1,
+                    N_TRIES,
+                    io::size_tag(),
+                    label,
+                    e
+                );
+                continue;
+            }
+        };
+        let g = solution.g;
+
+        let check = if options.cross_check {
+            let g_mesh = starmesh::network_conductance(N_UNK, all_bonds.iter().copied(), &resist);
+            format!("  |dG|/G={:<9.3e}", ((g - g_mesh) / g).abs())
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{}/{}  {:>5}  dist={}  time={:>5.3}s  iters={}  |err|2={:<9.3e}  shortcuts={}  G={:<9.3e}{}{}",
+            iter + 1,
+            N_TRIES,
+            io::size_tag(),
+            label,
+            duration.as_secs_f64(),
+            solution.iters,
+            solution.err2(),
+            n_shortcuts,
+            g,
+            check,
+            solution.isolated()
+        );
+
+        let out_file = outdir.join(format!("shortcuts_{}_{}.out", io::size_tag(), label));
+        io::append_line(&out_file, &format!("{} {}", g, n_shortcuts))
+            .expect("Failed at saving results");
+
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportArrays | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            let (err2, errinf) = (solution.err2(), solution.errinf());
+            io::export_arrays(&label, outdir, &resist, &solution.x, err2, errinf, iter)
+                .expect("Failed at saving results");
+            let pairs: Vec<String> = all_bonds[N_RES..]
+                .iter()
+                .map(|bond| match *bond {
+                    Bond::Internal(a, b) => {
+                        let ((ra, ca), (rb, cb)) = (coords(a), coords(b));
+                        format!("{} {} {} {}", ra, ca, rb, cb)
+                    }
+                    _ => unreachable!(),
+                })
+                .collect();
+            let sc_file = outdir.join(format!("{}_{}_{:04}.sc", io::size_tag(), label, iter));
+            io::append_line(&sc_file, &pairs.join("\n")).expect("Failed at saving results");
+        }
+        if matches!(
+            options.export_mode,
+            ExportMode::ExportIsoSurface | ExportMode::ExportArraysAndIsoSurface
+        ) {
+            let surface = compute_isosurface(&solution.x, value, &solution.active);
+            let surf_file = outdir.join(format!("isosurfaces_{}_{}.out", io::size_tag(), label));
+            io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lattice::fidx;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_weights() {
+        let shortcuts = Shortcuts {
+            alpha: 2.5,
+            density: 1.0,
+        };
+        let source = fidx(LX / 2, 2);
+        let weights = shortcuts.weights(source);
+
+        // Nothing to the node itself, its neighbours or its diagonals
+        for (row, col) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            assert_eq!(weights[fidx(LX / 2 + row, 2 + col)], 0.0);
+        }
+        let ratio = weights[fidx(LX / 2, 4)] / weights[fidx(LX / 2, 6)];
+        assert_relative_eq!(ratio, 2f64.powf(2.5), epsilon = 1e-12);
+
+        let mut rng = rand::rng();
+        let pairs = shortcuts.draw(&mut rng);
+        assert_eq!(pairs.len(), N_UNK);
+        assert!(pairs.iter().all(|&(a, b)| distance(a, b) > MIN_DISTANCE));
+
+        assert!(Shortcuts::with(2.5, 1.5).is_err());
+        assert!(Shortcuts::with(2.5, -0.1).is_err());
+        assert!(Shortcuts::with(f64::NAN, 0.1).is_err());
+    }
+
+    #[test]
+    fn test_shortcut_network() {
+        // A few shortcuts on unit bonds, against the star-mesh reduction of the same network
+        let shortcuts = Shortcuts {
+            alpha: 3.0,
+            density: 10.0 / N_UNK as f64,
+        };
+        let mut rng = rand::rng();
+        let pairs = shortcuts.draw(&mut rng);
+        let all_bonds: Vec<Bond> = bonds()
+            .chain(pairs.iter().map(|&(a, b)| Bond::Internal(a, b)))
+            .collect();
+        let resist: Vec<f64> = all_bonds
+            .iter()
+            .map(|_| 0.5 + rng.random::<f64>())
+            .collect();
+
+        let (x, _) = graph::solve(N_UNK, all_bonds.iter().copied(), &resist, 1e-12).unwrap();
+        let g = graph::conductance(all_bonds.iter().copied(), &resist, &x);
+        let g_mesh = starmesh::network_conductance(N_UNK, all_bonds.iter().copied(), &resist);
+        assert_relative_eq!(g, g_mesh, max_relative = 1e-8);
+    }
+}
@@ -1,21 +1,32 @@
-use crate::config::{Precision, L, N_RES, N_THREADS, N_TRIES, N_UNK};
+use crate::config::{Precision, LATTICE, LX, LY, N_RES, N_THREADS, N_TRIES, N_UNK};
 #[allow(unused_imports)]
 use crate::dists::Distribution;
+use crate::dtn::ResponseMatrix;
+use crate::electrodes::Electrodes;
 use crate::float::Float;
+use crate::green::GreenFunction;
+use crate::imagemap::Grayscale;
 use crate::io;
+use crate::lattice::{bonds, fidx, Bond};
+use crate::percolation;
+use crate::starmesh;
+use crate::tensor;
 use rand::rngs::ThreadRng;
 use std::cmp::min;
 use std::collections::VecDeque;
-use std::mem;
 use std::path::Path;
 use std::thread;
 use std::time::Duration;
 
-const V_HGH: Precision = Precision::ONE;
-const V_LOW: Precision = Precision::NEG_ONE;
-const ZERO: Precision = Precision::ZERO;
+pub(crate) const V_HGH: Precision = Precision::ONE;
+pub(crate) const V_LOW: Precision = Precision::NEG_ONE;
+pub(crate) const ZERO: Precision = Precision::ZERO;
 
-static mut MATRIX: [[Precision; N_UNK + 1]; N_UNK] = [[ZERO; N_UNK + 1]; N_UNK];
+pub(crate) static mut MATRIX: [[Precision; N_UNK + 1]; N_UNK] = [[ZERO; N_UNK + 1]; N_UNK];
+
+/// Serializes the tests that assemble their system in `MATRIX`.
+#[cfg(test)]
+pub(crate) static MATRIX_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
 #[allow(dead_code)]
 pub enum ExportMode {
@@ -25,83 +36,80 @@ pub enum ExportMode {
     ExportArraysAndIsoSurface,
 }
 
-#[inline]
-fn fidx(i: usize, j: usize) -> usize {
-    (i) + (j) * L
+/// Settings of a run of `compute_n_tries`.
+pub struct RunOptions {
+    pub export_mode: ExportMode,
+    pub isosurface_value: f64,
+    /// Compare every solution with the star-mesh reduction of the same sample
+    pub cross_check: bool,
+    /// Also solve every sample under horizontal and vertical drives for its conductance tensor
+    pub tensor: bool,
+    /// Export the Green's function column of this node (row, column) and its resistances to the
+    /// nodes to its right
+    pub green_source: Option<(usize, usize)>,
+    /// Export the response matrix of the bus terminals of every sample
+    pub dtn: bool,
+    /// Drive the samples through these electrodes instead of the buses
+    pub electrodes: Option<Electrodes>,
+    /// Image the conductances were read from, on which the isosurfaces are drawn
+    pub overlay: Option<Grayscale>,
 }
 
 fn fill_resistances<D: Distribution<Precision>>(
     dist: &D,
     rng: &mut ThreadRng,
-    resist: &mut Vec<Precision>,
+    resist: &mut [Precision],
 ) {
-    for x in resist.iter_mut() {
-        *x = dist.sample(rng);
-    }
+    dist.fill(rng, resist);
 }
 
-unsafe fn empty_matrix() {
-    for i in 0..N_UNK {
-        for j in 0..N_UNK + 1 {
-            MATRIX[i][j] = ZERO;
-        }
+#[allow(static_mut_refs)]
+pub(crate) unsafe fn empty_matrix() {
+    for row in MATRIX.iter_mut() {
+        row.fill(ZERO);
     }
 }
 
+#[allow(static_mut_refs)]
 unsafe fn swap_rows(row1: usize, row2: usize) {
-    for col in 0..N_UNK + 1 {
-        mem::swap(&mut MATRIX[row1][col], &mut MATRIX[row2][col]);
-    }
+    MATRIX.swap(row1, row2);
 }
 
-unsafe fn build_system(resist: &Vec<Precision>) {
-    let mut r_iter = resist.iter();
+/// Assembles the system of `resist` between the buses and returns the nodes left in it.
+pub(crate) unsafe fn build_system(resist: &[Precision]) -> Vec<bool> {
     empty_matrix();
 
-    // Vertical resistances
-    for j in 0..(L - 2) {
-        for i in 0..(L - 1) {
-            let r = r_iter.next().unwrap();
-            let hgh = fidx(i, j);
-            let low = fidx(i + 1, j);
-            MATRIX[hgh][hgh] += -r;
-            MATRIX[hgh][low] += r;
-            MATRIX[low][hgh] += r;
-            MATRIX[low][low] += -r;
-        }
-    }
-
-    // Internal horizontal resistances
-    for j in 0..(L - 3) {
-        for i in 0..L {
-            let r = r_iter.next().unwrap();
-            let hgh = fidx(i, j);
-            let low = fidx(i, j + 1);
-            MATRIX[hgh][hgh] += -r;
-            MATRIX[hgh][low] += r;
-            MATRIX[low][hgh] += r;
-            MATRIX[low][low] += -r;
+    for (bond, &r) in bonds().zip(resist) {
+        match bond {
+            Bond::Internal(hgh, low) => {
+                MATRIX[hgh][hgh] += -r;
+                MATRIX[hgh][low] += r;
+                MATRIX[low][hgh] += r;
+                MATRIX[low][low] += -r;
+            }
+            Bond::High(idx) => {
+                MATRIX[idx][idx] += -r;
+                MATRIX[idx][N_UNK] += -V_HGH * r;
+            }
+            Bond::Low(idx) => {
+                MATRIX[idx][idx] += -r;
+                MATRIX[idx][N_UNK] += -V_LOW * r;
+            }
         }
     }
 
-    // High horizontal resistances
-    for i in 0..L {
-        let r = r_iter.next().unwrap();
-        let idx = fidx(i, 0);
-        MATRIX[idx][idx] += -r;
-        MATRIX[idx][N_UNK] += -V_HGH * r;
+    // Clusters joined to neither bus are left out of the system, at zero potential
+    let active = percolation::connected(N_UNK, bonds(), resist, |_| false);
+    for idx in (0..N_UNK).filter(|&idx| !active[idx]) {
+        MATRIX[idx].fill(ZERO);
+        MATRIX[idx][idx] = Precision::ONE;
     }
 
-    // Low horizontal resistances
-    for i in 0..L {
-        let r = r_iter.next().unwrap();
-        let idx = fidx(i, L - 3);
-        MATRIX[idx][idx] += -r;
-        MATRIX[idx][N_UNK] += -V_LOW * r;
-    }
+    active
 }
 
-unsafe fn gauss_elimination(x: &mut Vec<Precision>) -> std::result::Result<(), String> {
+#[allow(clippy::needless_range_loop)]
+unsafe fn gauss_elimination(x: &mut [Precision]) -> std::result::Result<(), String> {
     let rows = N_UNK;
     let cols = N_UNK + 1;
 
@@ -125,7 +133,7 @@ unsafe fn gauss_elimination(x: &mut Vec<Precision>) -> std::result::Result<(), S
                         }
                         let factor = MATRIX[i][j] / MATRIX[j][j];
                         for k in j..cols {
-                            MATRIX[i][k] = MATRIX[i][k] - factor * MATRIX[j][k];
+                            MATRIX[i][k] -= factor * MATRIX[j][k];
                         }
                     })
                 })
@@ -148,13 +156,26 @@ unsafe fn gauss_elimination(x: &mut Vec<Precision>) -> std::result::Result<(), S
     Ok(())
 }
 
+/// Effective conductance between the buses, from the current entering through the high bus.
+fn compute_conductance(resist: &[Precision], x: &[Precision]) -> Precision {
+    let current = bonds()
+        .zip(resist)
+        .filter_map(|(bond, &r)| match bond {
+            Bond::High(idx) => Some(r * (V_HGH - x[idx])),
+            _ => None,
+        })
+        .fold(ZERO, |a, b| a + b);
+
+    current / (V_HGH - V_LOW)
+}
+
 enum Norm {
     Two,
     Inf,
 }
 
 #[allow(static_mut_refs)]
-unsafe fn compute_error(x: &Vec<Precision>, norm: Norm) -> Precision {
+unsafe fn compute_error(x: &[Precision], norm: Norm) -> Precision {
     let rows = MATRIX.len();
     let mut residual: Vec<Precision> = vec![ZERO; rows];
 
@@ -180,27 +201,26 @@ unsafe fn compute_error(x: &Vec<Precision>, norm: Norm) -> Precision {
     }
 }
 
-fn compute_isosurface(x: &Vec<Precision>, value: Precision) -> Vec<(usize, usize)> {
-    let mut surface: Vec<(usize, usize)> = Vec::with_capacity(L);
-
-    for row in 0..L {
-        for col in 0..L - 2 {
-            if x[fidx(row, col)] < value {
+/// Nodes below `value` next to a node above it, skipping the nodes that are not `active`.
+pub(crate) fn compute_isosurface(
+    x: &[Precision],
+    value: Precision,
+    active: &[bool],
+) -> Vec<(usize, usize)> {
+    let mut surface: Vec<(usize, usize)> = Vec::with_capacity(LX);
+
+    for row in 0..LX {
+        for col in 0..LY - 2 {
+            let idx = fidx(row, col);
+            if !active[idx] || x[idx].lt(value) {
                 continue;
             }
 
-            let neighs: Vec<(usize, usize)> = [
-                row.checked_sub(1).map(|r| (r, col)),    // Up
-                (row < L - 1).then_some((row + 1, col)), // Down
-                col.checked_sub(1).map(|c| (row, c)),    // Left
-                (col < L - 3).then_some((row, col + 1)), // Right
-            ]
-            .into_iter()
-            .flatten() // Remove None values
-            .collect();
+            let neighs = LATTICE.neighbours(row, col);
 
             'nloop: for neigh in neighs.iter() {
-                if x[fidx(neigh.0, neigh.1)] < value {
+                let n_idx = fidx(neigh.0, neigh.1);
+                if active[n_idx] && x[n_idx].lt(value) {
                     surface.push(*neigh);
                     break 'nloop;
                 }
@@ -211,6 +231,34 @@ fn compute_isosurface(x: &Vec<Precision>, value: Precision) -> Vec<(usize, usize
     surface
 }
 
+/// Appends the isosurface of `x` at `value` to the isosurface file, and draws it on the image of
+/// the conductances if they were read from one.
+fn export_isosurface(
+    x: &[Precision],
+    value: Precision,
+    active: &[bool],
+    outdir: &Path,
+    label: &str,
+    options: &RunOptions,
+    iter: usize,
+) {
+    let surface = compute_isosurface(x, value, active);
+    let surf_file = outdir.join(format!("isosurfaces_{}_{}.out", io::size_tag(), label));
+    io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+
+    if let Some(image) = &options.overlay {
+        let overlay_file = outdir.join(format!(
+            "overlay_{}_{}_{:04}.png",
+            io::size_tag(),
+            label,
+            iter
+        ));
+        image
+            .export_overlay(&surface, &overlay_file)
+            .expect("Failed at saving results");
+    }
+}
+
 fn compute_eta_and_completion_time(
     times: &VecDeque<Duration>,
     remaining_iters: usize,
@@ -234,7 +282,7 @@ fn compute_eta_and_completion_time(
     (eta, estimated_end_str)
 }
 
-pub fn compute_n_tries<D, P>(dist: D, outdir: P, export_mode: ExportMode, isosurface_value: f64)
+pub fn compute_n_tries<D, P>(dist: D, outdir: P, options: RunOptions)
 where
     D: Distribution<Precision>,
     P: AsRef<Path>,
@@ -244,12 +292,19 @@ where
     let mut rng = rand::rng();
     let outdir: &Path = outdir.as_ref();
     let mut times: VecDeque<Duration> = VecDeque::with_capacity(100);
-    let isosurface_value = Precision::new(isosurface_value);
+    let isosurface_value = Precision::new(options.isosurface_value);
+    let label = match &options.electrodes {
+        Some(electrodes) => format!("{}_el({})", dist, electrodes.name),
+        None => dist.to_string(),
+    };
 
     unsafe {
         for iter in 0..N_TRIES {
             let (duration_fill, _) = timeit!(fill_resistances(&dist, &mut rng, &mut resist));
-            let (duration_build, _) = timeit!(build_system(&mut resist));
+            let (duration_build, active) = timeit!(match &options.electrodes {
+                Some(electrodes) => electrodes.build_system(&resist),
+                None => build_system(&resist),
+            });
             let (duration_gauss, result) = timeit!(gauss_elimination(&mut x));
 
             let msg = match result.clone() {
@@ -260,6 +315,29 @@ where
             let err2 = compute_error(&x, Norm::Two);
             let errinf = compute_error(&x, Norm::Inf);
 
+            let check = if let (Some(electrodes), Ok(_)) = (&options.electrodes, &result) {
+                let currents = electrodes
+                    .currents(&resist, &x)
+                    .iter()
+                    .map(|i| format!("{:<9.3e}", i))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                format!("  I=[{}]", currents)
+            } else if options.cross_check && result.is_ok() {
+                let g = compute_conductance(&resist, &x);
+                let g_mesh = starmesh::effective_conductance(&resist);
+                format!("  G={:<9.3e}  |dG|/G={:<9.3e}", g, ((g - g_mesh) / g).abs())
+            } else {
+                String::new()
+            };
+
+            let n_isolated = active.iter().filter(|&&a| !a).count();
+            let isolated = if n_isolated > 0 {
+                format!("  isolated={}", n_isolated)
+            } else {
+                String::new()
+            };
+
             if times.len() >= 100 {
                 times.pop_front();
             }
@@ -270,14 +348,16 @@ where
                 compute_eta_and_completion_time(&times, N_TRIES - iter - 1);
 
             println!(
-                "{}/{}  L={:>3}  dist={}  time={:>5.3}s  |err|2={:<9.3e}  |err|inf={:<9.3e}  {}  ETA={}  completion={}",
+                "{}/{}  {:>5}  dist={}  time={:>5.3}s  |err|2={:<9.3e}  |err|inf={:<9.3e}{}{}  {}  ETA={}  completion={}",
                 iter + 1,
                 N_TRIES,
-                L,
-                dist,
+                io::size_tag(),
+                label,
                 duration.as_secs_f64(),
                 err2,
                 errinf,
+                check,
+                isolated,
                 msg,
                 eta,
                 completion_time
@@ -287,24 +367,81 @@ where
                 continue;
             }
 
-            match export_mode {
+            if options.tensor {
+                match tensor::conductance_tensor(&resist) {
+                    Ok(t) => {
+                        println!(
+                            "    sigma=[{:<9.3e} {:<9.3e}; {:<9.3e} {:<9.3e}]  anisotropy={:<9.3e}",
+                            t.sigma[0][0],
+                            t.sigma[0][1],
+                            t.sigma[1][0],
+                            t.sigma[1][1],
+                            t.anisotropy()
+                        );
+                        let tensor_file =
+                            outdir.join(format!("tensor_{}_{}.out", io::size_tag(), label));
+                        io::append_line(&tensor_file, &t.to_string())
+                            .expect("Failed at saving results");
+                    }
+                    Err(e) => println!("    FAIL - Conductance tensor failed. {:?}", e),
+                }
+            }
+
+            if let Some((row, col)) = options.green_source {
+                match GreenFunction::new(&resist) {
+                    Ok(green) => {
+                        let source = fidx(row, col);
+                        let column = green.column(source);
+                        let targets: Vec<usize> = (col + 1..LY - 2).map(|c| fidx(row, c)).collect();
+                        let resistances = green.resistances_from(source, &column, &targets);
+                        io::export_green(&dist, outdir, (row, col), &column, &resistances, iter)
+                            .expect("Failed at saving results");
+                    }
+                    Err(e) => println!("    FAIL - Green's function failed. {:?}", e),
+                }
+            }
+
+            if options.dtn {
+                match ResponseMatrix::new(&resist) {
+                    Ok(response) => {
+                        println!("    G_dtn={:<9.3e}", response.bus_conductance());
+                        io::export_response(&dist, outdir, &response, iter)
+                            .expect("Failed at saving results");
+                    }
+                    Err(e) => println!("    FAIL - Response matrix failed. {:?}", e),
+                }
+            }
+
+            match options.export_mode {
                 ExportMode::NoExport => continue,
                 ExportMode::ExportArrays => {
-                    io::export_arrays(&dist, &outdir, &resist, &x, err2, errinf, iter)
+                    io::export_arrays(&label, outdir, &resist, &x, err2, errinf, iter)
                         .expect("Failed at saving results");
                 }
                 ExportMode::ExportIsoSurface => {
-                    let surface = compute_isosurface(&x, isosurface_value);
-                    let surf_file = outdir.join(format!("isosurfaces_L{}_{}.out", L, dist));
-                    io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+                    export_isosurface(
+                        &x,
+                        isosurface_value,
+                        &active,
+                        outdir,
+                        &label,
+                        &options,
+                        iter,
+                    );
                 }
                 ExportMode::ExportArraysAndIsoSurface => {
-                    io::export_arrays(&dist, &outdir, &resist, &x, err2, errinf, iter)
+                    io::export_arrays(&label, outdir, &resist, &x, err2, errinf, iter)
                         .expect("Failed at saving results");
 
-                    let surface = compute_isosurface(&x, isosurface_value);
-                    let surf_file = outdir.join(format!("isosurfaces_L{}_{}.out", L, dist));
-                    io::export_surface(&surf_file, &surface).expect("Failed at saving results");
+                    export_isosurface(
+                        &x,
+                        isosurface_value,
+                        &active,
+                        outdir,
+                        &label,
+                        &options,
+                        iter,
+                    );
                 }
             }
         }
@@ -314,27 +451,109 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Precision;
+    use crate::config::{Precision, PERIODIC};
+    use crate::lattice::{coords, displacement};
     use crate::math;
+    use approx::assert_relative_eq;
 
     #[test]
     fn test_compute_surface() {
         let mut x: Vec<Precision> = vec![Precision::ZERO; N_UNK];
-        let values = math::linspace::<Precision>(V_LOW, V_HGH, L - 2);
+        let values = math::linspace::<Precision>(V_LOW, V_HGH, LY - 2);
         let isosurface_value = Precision::ZERO;
 
         for (j, v) in values.iter().enumerate() {
-            for i in 0..L {
+            for i in 0..LX {
                 let idx = fidx(i, j);
                 x[idx] = *v;
             }
         }
 
-        let surface = compute_isosurface(&x, isosurface_value);
-        let mid_column = (L - 2) / 2 - 1;
+        let surface = compute_isosurface(&x, isosurface_value, &[true; N_UNK]);
+        let mid_column = (LY - 2) / 2 - 1;
+        for (i, (row, col)) in surface.into_iter().enumerate() {
+            assert_eq!(row, i);
+            assert_eq!(col, mid_column);
+        }
+    }
+
+    #[test]
+    fn test_negative_isovalue() {
+        // Potentials rising along the rows, cut a quarter of a step above -0.5
+        let step = 2.0 / (LY - 3) as f64;
+        let value = -0.5 + step / 4.0;
+        let x: Vec<Precision> = (0..N_UNK)
+            .map(|idx| Precision::new(-1.0 + step * coords(idx).1 as f64))
+            .collect();
+        let below = (0..LY - 2)
+            .filter(|&col| -1.0 + step * (col as f64) < value)
+            .count();
+
+        let surface = compute_isosurface(&x, Precision::new(value), &[true; N_UNK]);
+        assert_eq!(surface.len(), LX);
+        assert!(surface.iter().all(|&(_, col)| col == below - 1));
+    }
+
+    #[test]
+    fn test_uniform_sample() {
+        // Unit bonds except for the diagonals: the potential falls linearly along the rows, the
+        // bonds inside the columns, those across the seam included, carry no current and every
+        // row crosses the isosurface in the middle
+        let resist: Vec<Precision> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b).0 * displacement(a, b).1 != 0 => ZERO,
+                _ => Precision::ONE,
+            })
+            .collect();
+        let mut x: Vec<Precision> = vec![ZERO; N_UNK];
+
+        let _lock = MATRIX_LOCK.lock().unwrap();
+        let active = unsafe {
+            let active = build_system(&resist);
+            gauss_elimination(&mut x).unwrap();
+            active
+        };
+        let expected = LX as f64 / (LY - 1) as f64;
+        assert_relative_eq!(
+            compute_conductance(&resist, &x).into_f64(),
+            expected,
+            epsilon = 1e-10
+        );
+
+        // Half a potential step above zero, away from the potential of any node
+        let value = Precision::new(0.5 / (LY - 1) as f64);
+        let surface = compute_isosurface(&x, value, &active);
+        let mid_column = (LY - 2) / 2;
+        assert_eq!(surface.len(), LX);
         for (i, (row, col)) in surface.into_iter().enumerate() {
             assert_eq!(row, i);
             assert_eq!(col, mid_column);
         }
     }
+
+    #[test]
+    fn test_seam_surface() {
+        // Only the last row is below the isovalue: the surface is reached from the row before it
+        // and, on a periodic lattice, from the first row across the seam
+        let mut x: Vec<Precision> = vec![V_HGH; N_UNK];
+        for col in 0..LY - 2 {
+            x[fidx(LX - 1, col)] = V_LOW;
+        }
+
+        let surface = compute_isosurface(&x, ZERO, &[true; N_UNK]);
+        assert!(surface.iter().all(|&(row, _)| row == LX - 1));
+
+        let reaching = |row: usize| {
+            (0..LY - 2)
+                .filter(|&col| {
+                    LATTICE
+                        .neighbours(row, col)
+                        .iter()
+                        .any(|&(r, _)| r == LX - 1)
+                })
+                .count()
+        };
+        assert_eq!(reaching(0) > 0, PERIODIC);
+        assert_eq!(surface.len(), reaching(LX - 2) + reaching(0));
+    }
 }
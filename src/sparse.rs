@@ -0,0 +1,171 @@
+//! Sparse symmetric systems in compressed sparse row form, solved with conjugate gradients.
+use crate::config::N_THREADS;
+use crate::float::Float;
+use std::thread;
+
+/// Below this number of rows the product with a vector is not split among threads.
+const MIN_PARALLEL_ROWS: usize = 4096;
+
+pub struct CsrMatrix<T: Float> {
+    pub n: usize,
+    row_ptr: Vec<usize>,
+    col_idx: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T: Float> CsrMatrix<T> {
+    /// Builds an `n x n` matrix from `(row, col, value)` entries, adding up repeated entries.
+    pub fn from_triplets(n: usize, mut triplets: Vec<(usize, usize, T)>) -> CsrMatrix<T> {
+        triplets.sort_by_key(|&(row, col, _)| (row, col));
+
+        let mut row_ptr = vec![0; n + 1];
+        let mut col_idx: Vec<usize> = Vec::with_capacity(triplets.len());
+        let mut values: Vec<T> = Vec::with_capacity(triplets.len());
+        let mut last: Option<(usize, usize)> = None;
+
+        for (row, col, value) in triplets {
+            if last == Some((row, col)) {
+                let v = values.last_mut().unwrap();
+                *v = *v + value;
+                continue;
+            }
+            last = Some((row, col));
+            row_ptr[row + 1] += 1;
+            col_idx.push(col);
+            values.push(value);
+        }
+
+        for row in 0..n {
+            row_ptr[row + 1] += row_ptr[row];
+        }
+
+        CsrMatrix {
+            n,
+            row_ptr,
+            col_idx,
+            values,
+        }
+    }
+
+    fn mul_rows(&self, x: &[T], y: &mut [T], first_row: usize) {
+        for (i, yi) in y.iter_mut().enumerate() {
+            let row = first_row + i;
+            let range = self.row_ptr[row]..self.row_ptr[row + 1];
+            *yi = self.col_idx[range.clone()]
+                .iter()
+                .zip(&self.values[range])
+                .fold(T::ZERO, |acc, (&j, &a)| acc + a * x[j]);
+        }
+    }
+
+    /// Computes `y = A x`.
+    pub fn mul_vec(&self, x: &[T], y: &mut [T]) {
+        if self.n < MIN_PARALLEL_ROWS {
+            self.mul_rows(x, y, 0);
+            return;
+        }
+
+        let chunk_size = self.n.div_ceil(N_THREADS);
+        thread::scope(|s| {
+            for (c, chunk) in y.chunks_mut(chunk_size).enumerate() {
+                s.spawn(move || self.mul_rows(x, chunk, c * chunk_size));
+            }
+        });
+    }
+
+    pub fn diagonal(&self) -> Vec<T> {
+        (0..self.n)
+            .map(|row| {
+                (self.row_ptr[row]..self.row_ptr[row + 1])
+                    .find(|&k| self.col_idx[k] == row)
+                    .map_or(T::ZERO, |k| self.values[k])
+            })
+            .collect()
+    }
+}
+
+fn dot<T: Float>(a: &[T], b: &[T]) -> T {
+    a.iter().zip(b).fold(T::ZERO, |acc, (&x, &y)| acc + x * y)
+}
+
+/// Solves `A x = b` for a symmetric positive definite `A` with the Jacobi-preconditioned
+/// conjugate gradient method, starting from the given `x`. Stops when the residual falls below
+/// `tol` times the norm of `b` and returns the number of iterations.
+pub fn conjugate_gradient<T: Float>(
+    a: &CsrMatrix<T>,
+    b: &[T],
+    x: &mut [T],
+    tol: T,
+    max_iter: usize,
+) -> Result<usize, String> {
+    let n = a.n;
+    let inv_diag: Vec<T> = a.diagonal().into_iter().map(|d| T::ONE / d).collect();
+
+    let mut ap = vec![T::ZERO; n];
+    a.mul_vec(x, &mut ap);
+    let mut r: Vec<T> = b.iter().zip(&ap).map(|(&b, &ax)| b - ax).collect();
+    let mut z: Vec<T> = r.iter().zip(&inv_diag).map(|(&r, &d)| r * d).collect();
+    let mut p = z.clone();
+    let mut rz = dot(&r, &z);
+
+    let threshold = tol * dot(b, b).sqrt();
+
+    for iter in 0..max_iter {
+        if dot(&r, &r).sqrt() <= threshold {
+            return Ok(iter);
+        }
+
+        a.mul_vec(&p, &mut ap);
+        let alpha = rz / dot(&p, &ap);
+        for i in 0..n {
+            x[i] = x[i] + alpha * p[i];
+            r[i] = r[i] - alpha * ap[i];
+            z[i] = r[i] * inv_diag[i];
+        }
+
+        let rz_new = dot(&r, &z);
+        let beta = rz_new / rz;
+        rz = rz_new;
+        for i in 0..n {
+            p[i] = z[i] + beta * p[i];
+        }
+    }
+
+    Err(format!(
+        "Conjugate gradient did not converge in {} iterations",
+        max_iter
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_conjugate_gradient() {
+        // Path graph Laplacian grounded at both ends, with a repeated entry to be summed
+        let n = 5;
+        let mut triplets: Vec<(usize, usize, f64)> = Vec::new();
+        for i in 0..n {
+            triplets.push((i, i, 2.0));
+            if i + 1 < n {
+                triplets.push((i, i + 1, -1.0));
+                triplets.push((i + 1, i, -1.0));
+            }
+        }
+        triplets.push((0, 0, 1.0));
+        let a = CsrMatrix::from_triplets(n, triplets);
+
+        let expected = [1.0, -2.0, 3.0, 0.5, -1.0];
+        let mut b = vec![0.0; n];
+        a.mul_vec(&expected, &mut b);
+
+        let mut x = vec![0.0; n];
+        let iters = conjugate_gradient(&a, &b, &mut x, 1e-12, 100).unwrap();
+        assert!(iters <= n);
+        for (x, e) in x.iter().zip(expected.iter()) {
+            assert_relative_eq!(x, e, epsilon = 1e-10);
+        }
+    }
+}
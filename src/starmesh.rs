@@ -0,0 +1,134 @@
+//! Frank–Lobb star–mesh reduction of the resistor network.
+//!
+//! Internal nodes are eliminated one by one, replacing each node and its star of bonds by the
+//! equivalent mesh between its neighbours, until only the bond joining the two buses is left.
+use crate::config::N_UNK;
+use crate::float::Float;
+use crate::lattice::{bonds, Bond};
+use std::collections::BTreeMap;
+use std::mem;
+
+fn connect<T: Float>(adj: &mut [BTreeMap<usize, T>], a: usize, b: usize, g: T) {
+    if g == T::ZERO {
+        return;
+    }
+
+    let ab = adj[a].entry(b).or_insert(T::ZERO);
+    *ab = *ab + g;
+    let ba = adj[b].entry(a).or_insert(T::ZERO);
+    *ba = *ba + g;
+}
+
+fn eliminate<T: Float>(adj: &mut [BTreeMap<usize, T>], k: usize) {
+    let star: Vec<(usize, T)> = mem::take(&mut adj[k]).into_iter().collect();
+    for (n, _) in star.iter() {
+        adj[*n].remove(&k);
+    }
+
+    let total = star.iter().fold(T::ZERO, |a, &(_, g)| a + g);
+    if total == T::ZERO {
+        return;
+    }
+
+    for (i, &(a, ga)) in star.iter().enumerate() {
+        for &(b, gb) in star[i + 1..].iter() {
+            connect(adj, a, b, ga * gb / total);
+        }
+    }
+}
+
+/// Effective conductance between the buses of a network of `n` nodes joined by `bonds` of
+/// conductances `resist`, eliminating the nodes in index order.
+pub fn network_conductance<T, B>(n: usize, bonds: B, resist: &[T]) -> T
+where
+    T: Float,
+    B: Iterator<Item = Bond>,
+{
+    let (hgh, low) = (n, n + 1);
+    let mut adj: Vec<BTreeMap<usize, T>> = vec![BTreeMap::new(); n + 2];
+
+    for (bond, &r) in bonds.zip(resist) {
+        let (a, b) = match bond {
+            Bond::Internal(a, b) => (a, b),
+            Bond::High(a) => (a, hgh),
+            Bond::Low(a) => (a, low),
+        };
+        connect(&mut adj, a, b, r);
+    }
+
+    for k in 0..n {
+        eliminate(&mut adj, k);
+    }
+
+    adj[hgh].get(&low).copied().unwrap_or(T::ZERO)
+}
+
+/// Effective conductance between the high and low buses.
+///
+/// Nodes are eliminated in `fidx` order, so the fill-in never spreads beyond one column.
+pub fn effective_conductance<T: Float>(resist: &[T]) -> T {
+    network_conductance(N_UNK, bonds(), resist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LX, LY, N_RES};
+    use crate::lattice::{displacement, Lattice};
+    use approx::assert_relative_eq;
+    use rand::Rng;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_uniform_network() {
+        // Unit bonds except for the diagonals
+        let resist: Vec<f64> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b).0 * displacement(a, b).1 != 0 => 0.0,
+                _ => 1.0,
+            })
+            .collect();
+
+        // Every row is a chain of LY - 1 bonds and no current flows through vertical bonds
+        let expected = LX as f64 / (LY - 1) as f64;
+        assert_relative_eq!(effective_conductance(&resist), expected, epsilon = EPS);
+    }
+
+    #[test]
+    fn test_uniform_rectangle() {
+        // Unit bonds between the columns of the same row on a lattice longer than it is wide,
+        // the other bonds removed: every row is a chain of cols + 1 bonds
+        let (rows, cols) = (6, 11);
+        let resist: Vec<f64> = Lattice::Square
+            .grid_bonds(rows, cols)
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if a % rows != b % rows => 0.0,
+                Bond::Internal(a, b) if a / rows == b / rows => 0.0,
+                _ => 1.0,
+            })
+            .collect();
+
+        let g = network_conductance(rows * cols, Lattice::Square.grid_bonds(rows, cols), &resist);
+        assert_relative_eq!(g, rows as f64 / (cols + 1) as f64, epsilon = EPS);
+    }
+
+    #[test]
+    fn test_independent_rows() {
+        let mut rng = rand::rng();
+        let mut resist: Vec<f64> = vec![0.0; N_RES];
+        let mut row_resistance: Vec<f64> = vec![0.0; LX];
+
+        for (bond, r) in bonds().zip(resist.iter_mut()) {
+            let row = match bond {
+                Bond::Internal(a, b) if displacement(a, b).0 != 0 => continue,
+                Bond::Internal(a, _) | Bond::High(a) | Bond::Low(a) => a % LX,
+            };
+            *r = 0.1 + rng.random::<f64>();
+            row_resistance[row] += 1.0 / *r;
+        }
+
+        let expected: f64 = row_resistance.iter().map(|r| 1.0 / r).sum();
+        assert_relative_eq!(effective_conductance(&resist), expected, epsilon = EPS);
+    }
+}
@@ -0,0 +1,232 @@
+//! Effective conductance tensor from two orthogonal drives.
+//!
+//! Each drive imposes on the boundary of the sample the potential of a uniform field, along the
+//! rows (horizontal) or along the columns (vertical). The boundary is made of the first and last
+//! rows of nodes and the outer ends of the bus bonds, which for the horizontal drive sit at the
+//! bus potentials as usual. The matrix of the interior nodes is the same for both drives, so it is
+//! factorized once and only the right-hand sides change.
+//!
+//! On a periodic lattice there are no boundary rows. The vertical drive is then imposed as a jump
+//! of the potential across the seam, the drop of the field over one period, so that the potential
+//! minus the uniform field is periodic.
+use crate::config::{Precision, LX, LY, N_UNK, PERIODIC};
+use crate::float::Float;
+use crate::lattice::{bonds, coords, displacement, Bond};
+use crate::linalg::{lu_factorize, lu_solve};
+use crate::percolation;
+use crate::solver::{empty_matrix, MATRIX, V_HGH, V_LOW, ZERO};
+use std::fmt;
+
+const HORIZONTAL: usize = 0;
+const VERTICAL: usize = 1;
+
+/// Effective conductances `sigma[a][b]`: current along `a` under a unit field along `b`, with the
+/// bond currents projected along `a` in lattice coordinates and normalized so that a uniform
+/// square network of unit bonds gives the identity.
+pub struct ConductanceTensor {
+    pub sigma: [[Precision; 2]; 2],
+}
+
+impl ConductanceTensor {
+    /// Ratio between the horizontal and vertical conductances
+    pub fn anisotropy(&self) -> Precision {
+        self.sigma[HORIZONTAL][HORIZONTAL] / self.sigma[VERTICAL][VERTICAL]
+    }
+}
+
+impl fmt::Display for ConductanceTensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.sigma[0][0],
+            self.sigma[0][1],
+            self.sigma[1][0],
+            self.sigma[1][1],
+            self.anisotropy()
+        )
+    }
+}
+
+/// Field of both drives, from the high to the low bus potential across the sample.
+fn field() -> Precision {
+    (V_HGH - V_LOW) / Precision::new((LY - 1) as f64)
+}
+
+/// Potential of `drive` at column `x` and row `y`, with the buses at `x = 0` and `x = LY - 1`.
+fn potential(drive: usize, x: usize, y: usize) -> Precision {
+    let r = if drive == HORIZONTAL { x } else { y };
+    V_HGH - field() * Precision::new(r as f64)
+}
+
+fn node_potential(drive: usize, idx: usize) -> Precision {
+    potential(drive, idx / LX + 1, idx % LX)
+}
+
+fn is_boundary(idx: usize) -> bool {
+    let row = idx % LX;
+    !PERIODIC && (row == 0 || row == LX - 1)
+}
+
+/// Potential added to `b` when the bond `(a, b)` is crossed from `a` under `drive`, nonzero only
+/// for the bonds across the seam of a periodic lattice.
+fn seam_jump(drive: usize, a: usize, b: usize) -> Precision {
+    if drive != VERTICAL {
+        return ZERO;
+    }
+    let (d_row, _) = displacement(a, b);
+    let wrap = coords(a).0 as isize + d_row - coords(b).0 as isize;
+    -field() * Precision::new(wrap as f64)
+}
+
+/// Assembles the matrix of both drives, with identity rows for the boundary nodes, and returns
+/// the right-hand side of each drive and the nodes left in the system.
+#[allow(static_mut_refs)]
+unsafe fn build_tensor_system(resist: &[Precision]) -> ([Vec<Precision>; 2], Vec<bool>) {
+    let mut rhs = [vec![ZERO; N_UNK], vec![ZERO; N_UNK]];
+    empty_matrix();
+
+    for idx in (0..N_UNK).filter(|&idx| is_boundary(idx)) {
+        MATRIX[idx][idx] = Precision::ONE;
+        for (drive, b) in rhs.iter_mut().enumerate() {
+            b[idx] = node_potential(drive, idx);
+        }
+    }
+
+    // Clusters joined to no fixed potential are left out of the system, at zero potential
+    let active = percolation::connected(N_UNK, bonds(), resist, is_boundary);
+    for idx in (0..N_UNK).filter(|&idx| !active[idx]) {
+        MATRIX[idx][idx] = Precision::ONE;
+    }
+
+    for (bond, &r) in bonds().zip(resist) {
+        let (idx, x) = match bond {
+            Bond::Internal(a, b) => {
+                for (p, q, sign) in [(a, b, Precision::ONE), (b, a, Precision::NEG_ONE)] {
+                    if !is_boundary(p) && active[p] {
+                        MATRIX[p][p] += -r;
+                        MATRIX[p][q] += r;
+                        for (drive, v) in rhs.iter_mut().enumerate() {
+                            v[p] += -sign * seam_jump(drive, a, b) * r;
+                        }
+                    }
+                }
+                continue;
+            }
+            Bond::High(idx) => (idx, 0),
+            Bond::Low(idx) => (idx, LY - 1),
+        };
+
+        if !is_boundary(idx) && active[idx] {
+            MATRIX[idx][idx] += -r;
+            for (drive, b) in rhs.iter_mut().enumerate() {
+                b[idx] += -potential(drive, x, idx % LX) * r;
+            }
+        }
+    }
+
+    (rhs, active)
+}
+
+/// Sum over the bonds of the current times its projection along each direction, and sum of the
+/// squared projections.
+fn currents(
+    resist: &[Precision],
+    x: &[Precision],
+    active: &[bool],
+    drive: usize,
+) -> ([Precision; 2], [isize; 2]) {
+    let mut current = [ZERO; 2];
+    let mut norm = [0; 2];
+
+    for (bond, &r) in bonds().zip(resist) {
+        let ((d_row, d_col), i) = match bond {
+            Bond::Internal(a, b) if !active[a] => (displacement(a, b), ZERO),
+            Bond::Internal(a, b) => (
+                displacement(a, b),
+                r * (x[a] - x[b] - seam_jump(drive, a, b)),
+            ),
+            Bond::High(a) => ((0, 1), r * (potential(drive, 0, a % LX) - x[a])),
+            Bond::Low(a) => ((0, 1), r * (x[a] - potential(drive, LY - 1, a % LX))),
+        };
+        for (dir, d) in [(HORIZONTAL, d_col), (VERTICAL, d_row)] {
+            current[dir] += i * Precision::new(d as f64);
+            norm[dir] += d * d;
+        }
+    }
+
+    (current, norm)
+}
+
+/// Solves the sample under the horizontal and vertical drives and returns its tensor.
+#[allow(static_mut_refs)]
+pub unsafe fn conductance_tensor(resist: &[Precision]) -> Result<ConductanceTensor, String> {
+    let (rhs, active) = build_tensor_system(resist);
+    let perm = lu_factorize(&mut MATRIX[..], N_UNK)?;
+    let mut sigma = [[ZERO; 2]; 2];
+
+    for (drive, b) in rhs.into_iter().enumerate() {
+        let mut x = b;
+        lu_solve(&MATRIX[..], &perm, &mut x);
+
+        let (current, norm) = currents(resist, &x, &active, drive);
+        for dir in [HORIZONTAL, VERTICAL] {
+            sigma[dir][drive] = current[dir] / (Precision::new(norm[dir] as f64) * field());
+        }
+    }
+
+    Ok(ConductanceTensor { sigma })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DIAGONALS, LATTICE};
+    use crate::lattice::Lattice;
+    use crate::solver::MATRIX_LOCK;
+    use approx::assert_relative_eq;
+
+    const EPS: f64 = 1e-10;
+
+    /// Tensor of a network whose horizontal bonds have conductance `g` and vertical ones `g * k`.
+    fn tensor_of(g: f64, k: f64) -> [[f64; 2]; 2] {
+        let resist: Vec<Precision> = bonds()
+            .map(|bond| match bond {
+                Bond::Internal(a, b) if displacement(a, b).0 != 0 => Precision::new(g * k),
+                _ => Precision::new(g),
+            })
+            .collect();
+        let _lock = MATRIX_LOCK.lock().unwrap();
+        let t = unsafe { conductance_tensor(&resist).unwrap() };
+        t.sigma.map(|row| row.map(|s| s.into_f64()))
+    }
+
+    #[test]
+    fn test_uniform_tensor() {
+        // The normalization only makes the plain square network isotropic
+        if LATTICE != Lattice::Square || DIAGONALS {
+            return;
+        }
+        let sigma = tensor_of(0.5, 1.0);
+        assert_relative_eq!(sigma[HORIZONTAL][HORIZONTAL], 0.5, epsilon = EPS);
+        assert_relative_eq!(sigma[VERTICAL][VERTICAL], 0.5, epsilon = EPS);
+        assert_relative_eq!(sigma[HORIZONTAL][VERTICAL], 0.0, epsilon = EPS);
+        assert_relative_eq!(sigma[VERTICAL][HORIZONTAL], 0.0, epsilon = EPS);
+    }
+
+    #[test]
+    fn test_anisotropic_tensor() {
+        // The normalization only makes the plain square network isotropic
+        if LATTICE != Lattice::Square || DIAGONALS {
+            return;
+        }
+        let k = 3.0;
+        let sigma = tensor_of(1.0, k);
+        assert_relative_eq!(
+            sigma[VERTICAL][VERTICAL] / sigma[HORIZONTAL][HORIZONTAL],
+            k,
+            epsilon = EPS
+        );
+        assert_relative_eq!(sigma[HORIZONTAL][VERTICAL], 0.0, epsilon = EPS);
+    }
+}
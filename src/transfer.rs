@@ -0,0 +1,399 @@
+//! Transfer-matrix sweep for the conductance of long strips.
+//!
+//! The strip has `width` rows and `length` columns of nodes between the two buses, laid out as
+//! in `fidx`. Columns are added one at a time and the previous one is eliminated, so only the
+//! Schur complement of the swept part onto the last column and the high bus is kept in memory.
+use crate::config::{Precision, LATTICE, PERIODIC};
+use crate::dists::Distribution;
+use crate::float::Float;
+use crate::io;
+use crate::lattice::{Lattice, Orientation};
+use crate::linalg::{lu_factorize, lu_solve};
+use std::path::Path;
+
+/// Conductance of a strip together with its resistance per column.
+pub struct StripResult<T: Float> {
+    /// Conductance between the two buses
+    pub conductance: T,
+    /// Resistance per unit length, from the mean increment over blocks of columns
+    pub rho: T,
+    /// Standard error of `rho` over the blocks
+    pub rho_err: T,
+}
+
+impl<T: Float> StripResult<T> {
+    /// Conductance per unit length, 1 / rho
+    pub fn conductivity(&self) -> T {
+        T::ONE / self.rho
+    }
+
+    pub fn conductivity_err(&self) -> T {
+        self.rho_err / (self.rho * self.rho)
+    }
+}
+
+/// Nodes among the first `w` of `full` joined, directly or through each other, to none of the
+/// rest: the closed clusters left behind by diluted bonds.
+fn closed_nodes<T: Float>(full: &[Vec<T>], w: usize) -> Vec<bool> {
+    let mut open: Vec<bool> = (0..w)
+        .map(|i| full[i][w..].iter().any(|&g| g != T::ZERO))
+        .collect();
+    let mut stack: Vec<usize> = (0..w).filter(|&i| open[i]).collect();
+    while let Some(i) = stack.pop() {
+        for j in 0..w {
+            if !open[j] && full[i][j] != T::ZERO {
+                open[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+    open.into_iter().map(|o| !o).collect()
+}
+
+/// Connects `m` new nodes to the last column of `k` and eliminates that column.
+///
+/// `k` is the reduced Laplacian over the last column followed by the high bus. Each link
+/// `(i, t, g)` joins node `i` of the column to new node `t` with conductance `g`, and `new_block`
+/// is the Laplacian of the bonds among the new nodes. The result is ordered in the same way.
+fn sweep<T: Float>(
+    k: &[Vec<T>],
+    links: &[(usize, usize, T)],
+    new_block: &[Vec<T>],
+) -> Result<Vec<Vec<T>>, String> {
+    let w = k.len() - 1;
+    let m = new_block.len();
+    let bus = w + m;
+    let n = bus + 1;
+
+    let mut full: Vec<Vec<T>> = vec![vec![T::ZERO; n]; n];
+    for i in 0..w {
+        for j in 0..w {
+            full[i][j] = k[i][j];
+        }
+        full[i][bus] = k[i][w];
+        full[bus][i] = k[w][i];
+    }
+    full[bus][bus] = k[w][w];
+
+    for i in 0..m {
+        for j in 0..m {
+            full[w + i][w + j] = new_block[i][j];
+        }
+    }
+
+    for &(i, t, g) in links {
+        let t = w + t;
+        full[i][i] = full[i][i] + g;
+        full[t][t] = full[t][t] + g;
+        full[i][t] = full[i][t] - g;
+        full[t][i] = full[t][i] - g;
+    }
+
+    // Closed clusters are decoupled from the rest and would make A singular
+    for (i, _) in closed_nodes(&full, w)
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, c)| c)
+    {
+        full[i][..w].fill(T::ZERO);
+        for row in full[..w].iter_mut() {
+            row[i] = T::ZERO;
+        }
+        full[i][i] = T::ONE;
+    }
+
+    // Schur complement onto the new nodes and the bus: R - C^T A^{-1} C
+    let mut a: Vec<Vec<T>> = full[..w].iter().map(|row| row[..w].to_vec()).collect();
+    let perm = lu_factorize(&mut a, w)?;
+
+    let mut reduced: Vec<Vec<T>> = full[w..].iter().map(|row| row[w..].to_vec()).collect();
+    for c in 0..=m {
+        let mut x: Vec<T> = (0..w).map(|i| full[i][w + c]).collect();
+        lu_solve(&a, &perm, &mut x);
+        for (r, row) in reduced.iter_mut().enumerate() {
+            let dot = (0..w).fold(T::ZERO, |acc, i| acc + full[w + r][i] * x[i]);
+            row[c] = row[c] - dot;
+        }
+    }
+
+    Ok(reduced)
+}
+
+/// Laplacian of the bonds `vert` among the `w` rows of a column.
+fn column_block<T: Float>(w: usize, vert: &[(usize, usize, T)]) -> Vec<Vec<T>> {
+    let mut block = vec![vec![T::ZERO; w]; w];
+    for &(i, j, g) in vert {
+        block[i][i] = block[i][i] + g;
+        block[j][j] = block[j][j] + g;
+        block[i][j] = block[i][j] - g;
+        block[j][i] = block[j][i] - g;
+    }
+    block
+}
+
+/// Checks that a strip of `width` rows and `length` columns can be split into `n_blocks` blocks.
+pub fn check_strip(width: usize, length: usize, n_blocks: usize) -> Result<(), String> {
+    if n_blocks < 3 {
+        return Err(String::from(
+            "At least three blocks are needed to estimate the error.",
+        ));
+    }
+    if length < n_blocks {
+        return Err(format!(
+            "Strip length {} must be at least the number of blocks {}.",
+            length, n_blocks
+        ));
+    }
+    if !PERIODIC && width < 2 {
+        return Err(format!("Strip width {} must be at least 2.", width));
+    }
+    if PERIODIC && (width < 3 || (LATTICE == Lattice::Honeycomb && !width.is_multiple_of(2))) {
+        return Err(String::from(
+            "Periodic strips need a width above 2, and an even one for the honeycomb lattice.",
+        ));
+    }
+    Ok(())
+}
+
+/// Sweeps a strip of `width` rows and `length` columns, drawing its bonds from `next` given
+/// their orientation.
+///
+/// Bonds are drawn column by column: first the bonds from the high bus to column 0, then for
+/// every column the bonds inside it followed by the bonds to the next column, as given by the
+/// lattice, or the `width` bonds to the low bus after the last one. The resistance between the high bus
+/// and each column, taken as an equipotential, grows linearly with the column; its increments
+/// over `n_blocks` blocks give the resistance per unit length and its self-averaging error.
+pub fn strip_conductance<T, F>(
+    width: usize,
+    length: usize,
+    n_blocks: usize,
+    mut next: F,
+) -> Result<StripResult<T>, String>
+where
+    T: Float,
+    F: FnMut(Orientation) -> T,
+{
+    check_strip(width, length, n_blocks)?;
+    let block_len = length / n_blocks;
+
+    let mut k: Vec<Vec<T>> = Vec::new();
+    let horiz: Vec<T> = (0..width).map(|_| next(Orientation::Horizontal)).collect();
+    let mut links: Vec<(usize, usize, T)> = Vec::new();
+    let mut checkpoints: Vec<T> = Vec::with_capacity(n_blocks + 1);
+
+    for col in 0..length {
+        let vert: Vec<(usize, usize, T)> = LATTICE
+            .column_bonds(width, col)
+            .map(|(i, j)| (i, j, next(Orientation::Vertical)))
+            .collect();
+        k = if col == 0 {
+            // The bus is a single node, every bond of column 0 ends in it
+            let mut first = vec![vec![T::ZERO; width + 1]; width + 1];
+            let block = column_block(width, &vert);
+            for i in 0..width {
+                for j in 0..width {
+                    first[i][j] = block[i][j];
+                }
+                let g = horiz[i];
+                first[i][i] = first[i][i] + g;
+                first[width][width] = first[width][width] + g;
+                first[i][width] = first[i][width] - g;
+                first[width][i] = first[width][i] - g;
+            }
+            first
+        } else {
+            sweep(&k, &links, &column_block(width, &vert))?
+        };
+
+        if (col + 1) % block_len == 0 && checkpoints.len() < n_blocks {
+            checkpoints.push(T::ONE / k[width][width]);
+        }
+
+        links = if col + 1 < length {
+            LATTICE
+                .link_bonds(width)
+                .map(|(i, j)| {
+                    let orientation = if i == j {
+                        Orientation::Horizontal
+                    } else {
+                        Orientation::Diagonal
+                    };
+                    (i, j, next(orientation))
+                })
+                .collect()
+        } else {
+            // The low bus is a single node joined to every row of the last column
+            (0..width)
+                .map(|i| (i, 0, next(Orientation::Horizontal)))
+                .collect()
+        };
+    }
+
+    let k = sweep(&k, &links, &[vec![T::ZERO]])?;
+    let conductance = k[1][1];
+
+    let n = T::new((checkpoints.len() - 1) as f64);
+    let len = T::new(block_len as f64);
+    let increments: Vec<T> = checkpoints
+        .windows(2)
+        .map(|w| (w[1] - w[0]) / len)
+        .collect();
+    let rho = increments.iter().fold(T::ZERO, |a, &b| a + b) / n;
+    let var = increments
+        .iter()
+        .map(|&d| (d - rho) * (d - rho))
+        .fold(T::ZERO, |a, b| a + b)
+        / (n - T::ONE);
+
+    Ok(StripResult {
+        conductance,
+        rho,
+        rho_err: (var / n).sqrt(),
+    })
+}
+
+pub fn compute_strip<D, P>(dist: D, width: usize, length: usize, n_blocks: usize, outdir: P)
+where
+    D: Distribution<Precision>,
+    P: AsRef<Path>,
+{
+    let mut rng = rand::rng();
+    let (duration, result) = timeit!(strip_conductance::<Precision, _>(
+        width,
+        length,
+        n_blocks,
+        |orientation| dist.sample_along(orientation, &mut rng)
+    ));
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            println!("W={}  N={}  dist={}  FAIL - {}", width, length, dist, e);
+            return;
+        }
+    };
+
+    println!(
+        "W={}  N={}  dist={}  time={:>5.3}s  G={:<9.3e}  rho={:<9.3e} +- {:<9.3e}  1/rho={:<9.3e} +- {:<9.3e}",
+        width,
+        length,
+        dist,
+        duration.as_secs_f64(),
+        result.conductance,
+        result.rho,
+        result.rho_err,
+        result.conductivity(),
+        result.conductivity_err(),
+    );
+
+    let out_file = outdir
+        .as_ref()
+        .join(format!("strip_W{}_N{}_{}.out", width, length, dist));
+    let line = format!(
+        "{} {} {} {} {}",
+        result.conductance,
+        result.rho,
+        result.rho_err,
+        result.conductivity(),
+        result.conductivity_err()
+    );
+    io::append_line(&out_file, &line).expect("Failed at saving results");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LX, LY, N_RES, N_UNK};
+    use crate::lattice::{bonds, fidx, Bond};
+    use crate::starmesh;
+    use approx::assert_relative_eq;
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_uniform_strip() {
+        let (width, length) = (8, 64);
+
+        // Unit bonds except for the diagonals, in the order in which the strip draws them
+        let mut order = (0..width).map(|_| 1.0).chain((0..length).flat_map(|col| {
+            let vert = LATTICE.column_bonds(width, col).map(|_| 1.0);
+            let links: Vec<f64> = match col {
+                col if col == length - 1 => vec![1.0; width],
+                _ => LATTICE
+                    .link_bonds(width)
+                    .map(|(a, b)| if a == b { 1.0 } else { 0.0 })
+                    .collect(),
+            };
+            vert.chain(links)
+        }));
+        let result =
+            strip_conductance::<f64, _>(width, length, 8, |_| order.next().unwrap()).unwrap();
+
+        // Every row is a chain of length + 1 bonds
+        let expected = width as f64 / (length + 1) as f64;
+        assert_relative_eq!(result.conductance, expected, epsilon = EPS);
+        assert_relative_eq!(result.conductivity(), width as f64, epsilon = EPS);
+        assert_relative_eq!(result.rho_err, 0.0, epsilon = EPS);
+
+        assert!(check_strip(width, length, 0).is_err());
+        assert!(check_strip(width, length, 2).is_err());
+        assert!(check_strip(width, 4, 8).is_err());
+        assert!(check_strip(0, length, 8).is_err());
+        assert!(check_strip(1, length, 8).is_err());
+    }
+
+    #[test]
+    fn test_matches_starmesh() {
+        let mut rng = rand::rng();
+        let resist: Vec<f64> = (0..N_RES).map(|_| 0.1 + rng.random::<f64>()).collect();
+        assert_matches_starmesh(&resist);
+    }
+
+    #[test]
+    fn test_diluted_matches_starmesh() {
+        // Enough bonds removed to leave closed clusters behind, but not so many as to break the
+        // paths between the buses
+        let mut rng = rand::rng();
+        let resist: Vec<f64> = (0..N_RES)
+            .map(|_| match rng.random::<f64>() < 0.3 {
+                true => 0.0,
+                false => 0.1 + rng.random::<f64>(),
+            })
+            .collect();
+        assert_matches_starmesh(&resist);
+    }
+
+    fn assert_matches_starmesh(resist: &[f64]) {
+        let (hgh, low) = (N_UNK, N_UNK + 1);
+        let values: HashMap<(usize, usize), f64> = bonds()
+            .zip(resist.iter())
+            .map(|(bond, &r)| match bond {
+                Bond::Internal(a, b) => ((a, b), r),
+                Bond::High(a) => ((hgh, a), r),
+                Bond::Low(a) => ((a, low), r),
+            })
+            .collect();
+
+        // Same bonds in the order in which the strip draws them
+        let order = (0..LX).map(|i| (hgh, i)).chain((0..LY - 2).flat_map(|j| {
+            let vert: Vec<(usize, usize)> = LATTICE
+                .column_bonds(LX, j)
+                .map(|(a, b)| (fidx(a, j), fidx(b, j)))
+                .collect();
+            let links: Vec<(usize, usize)> = match j {
+                j if j == LY - 3 => (0..LX).map(|i| (fidx(i, j), low)).collect(),
+                j => LATTICE
+                    .link_bonds(LX)
+                    .map(|(a, b)| (fidx(a, j), fidx(b, j + 1)))
+                    .collect(),
+            };
+            vert.into_iter().chain(links)
+        }));
+        let mut order = order.map(|key| values[&key]);
+
+        let result = strip_conductance::<f64, _>(LX, LY - 2, 3, |_| order.next().unwrap()).unwrap();
+        let expected = starmesh::effective_conductance(resist);
+        assert_relative_eq!(result.conductance, expected, epsilon = EPS);
+    }
+}
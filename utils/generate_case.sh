@@ -1,18 +1,22 @@
 generate_config() {
     echo "#[allow(unused)]"                                                                   > $4
     echo "use f256::f256;"                                                                    >> $4
+    echo "use crate::lattice::Lattice;"                                                       >> $4
     echo ""                                                                                   >> $4
     echo "//***************************************************************************//"    >> $4
     echo ""                                                                                   >> $4
     echo "pub type Precision = $2;"                                                           >> $4
-    echo "pub const L: usize = $1;"                                                           >> $4
+    echo "pub const LX: usize = $1;"                                                          >> $4
+    echo "pub const LY: usize = $1;"                                                          >> $4
+    echo "pub const PERIODIC: bool = false;"                                                  >> $4
+    echo "pub const LATTICE: Lattice = Lattice::Square;"                                      >> $4
     echo "pub const N_THREADS: usize = 16;"                                                   >> $4
     echo "pub const N_TRIES: usize = $3;"                                                     >> $4
     echo ""                                                                                   >> $4
     echo "//***************************************************************************//"    >> $4
     echo ""                                                                                   >> $4
-    echo "pub const N_RES: usize = (L - 1) * (L - 2) + L * (L - 2) + 2 * L;"                  >> $4
-    echo "pub const N_UNK: usize = L * (L - 2);"                                              >> $4
+    echo "pub const N_RES: usize = LATTICE.n_bonds(LX, LY - 2);"                              >> $4
+    echo "pub const N_UNK: usize = LX * (LY - 2);"                                            >> $4
 }
 
 compile_cases() {